@@ -0,0 +1,293 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::io::path::Path as ParsedPath;
+use crate::io::{FileLocale, Meta, Value, Workspace};
+use crate::render::{RenderOptions, Renderer};
+use crate::script::BashOptions;
+
+/// Options which define how a workspace is built.
+#[derive(Debug, Clone)]
+pub struct BuildOptions {
+    /// Directory to write the website into.
+    pub output: PathBuf,
+    pub render: RenderOptions,
+    /// Copy the variant of each asset that best fits a locale into the directory of that locale,
+    /// instead of copying all assets as-is. Disabled by default, as a suffix between the name
+    /// and extension of an asset which is one of the site locales is seen as its locale.
+    pub localized_assets: bool,
+}
+
+impl BuildOptions {
+    /// Options as configured by the site configuration of the workspace found at the given root,
+    /// using the defaults for all keys which are not configured.
+    pub fn from_config(root: &Path, config: &Meta) -> Result<BuildOptions> {
+        let defaults = BashOptions::default();
+        Ok(BuildOptions {
+            output: root.join(config_string(config, "output")?.unwrap_or("public")),
+            render: RenderOptions {
+                bash: BashOptions {
+                    enabled: config_bool(config, "bash.enabled")?.unwrap_or(defaults.enabled),
+                    timeout: config_number(config, "bash.timeout")?
+                        .map(Duration::from_secs_f64)
+                        .unwrap_or(defaults.timeout),
+                },
+            },
+            localized_assets: config_bool(config, "localized_assets")?.unwrap_or_default(),
+        })
+    }
+}
+
+/// Render all pages of the workspace found at the given root,
+/// and write them together with the assets of that workspace into the output directory.
+/// The given overrides take precedence over the values of the site configuration.
+/// Pages are rendered once for the default locale, written to the root of the output directory,
+/// and once more for each other locale found within the workspace, written to the `<locale>` directory.
+pub fn build<P: AsRef<Path>>(root: P, overrides: &[(String, Value)]) -> Result<()> {
+    let mut workspace = Workspace::read(root.as_ref())
+        .with_context(|| format!("failed to read workspace {}", root.as_ref().display()))?;
+    for (key, value) in overrides {
+        let path = ParsedPath::parse(key).with_context(|| format!("invalid config key {}", key))?;
+        if !workspace.config_mut().set_value(&path, value.clone()) {
+            return Err(anyhow!("cannot override config key {}", key));
+        }
+    }
+    let options = BuildOptions::from_config(workspace.root(), workspace.config())?;
+    let output = options.output.as_path();
+    let locales = workspace.site_locales()?;
+
+    let assets_dir = workspace.root().join("assets");
+    let assets: Vec<(PathBuf, PathBuf)> = if options.localized_assets {
+        let localized_assets = workspace.localized_assets()?;
+        locales
+            .iter()
+            .enumerate()
+            .flat_map(|(index, locale)| {
+                let dir = locale_output_dir(output, index, locale.as_ref());
+                localized_assets.iter().flat_map(move |asset| {
+                    match asset.resolve(locale.as_ref()) {
+                        Some(src) => vec![(src.to_path_buf(), dir.join(asset.path()))],
+                        // an asset without a variant for the locale is copied unchanged
+                        None => asset
+                            .variant_paths()
+                            .map(|src| (src.to_path_buf(), dir.join(src)))
+                            .collect(),
+                    }
+                })
+            })
+            .collect()
+    } else {
+        workspace
+            .assets()
+            .iter()
+            .map(|asset| (asset.clone(), output.join(asset)))
+            .collect()
+    };
+
+    let mut renderer = Renderer::with_options(workspace, options.render);
+    for (index, locale) in locales.into_iter().enumerate() {
+        let output = locale_output_dir(output, index, locale.as_ref());
+        renderer.set_locale(locale);
+        for page in renderer.pages() {
+            let rendered_pages = renderer
+                .render_page(&page)
+                .with_context(|| format!("failed to render page {}", page.info().path()))?;
+            for rendered_page in rendered_pages {
+                write_file(output.join(rendered_page.path()), rendered_page.content())?;
+            }
+        }
+    }
+
+    for (asset, path) in &assets {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(assets_dir.join(asset), path)
+            .with_context(|| format!("failed to copy asset {}", asset.display()))?;
+    }
+
+    Ok(())
+}
+
+fn config_string<'a>(config: &'a Meta, key: &str) -> Result<Option<&'a str>> {
+    match config.value(&ParsedPath::parse(key)?) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(anyhow!("config key {} is expected to be a string", key)),
+    }
+}
+
+fn config_bool(config: &Meta, key: &str) -> Result<Option<bool>> {
+    match config.value(&ParsedPath::parse(key)?) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Boolean(b)) => Ok(Some(*b)),
+        Some(_) => Err(anyhow!("config key {} is expected to be a boolean", key)),
+    }
+}
+
+fn config_number(config: &Meta, key: &str) -> Result<Option<f64>> {
+    match config.value(&ParsedPath::parse(key)?) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(x)) if *x >= 0.0 && x.is_finite() => Ok(Some(*x)),
+        Some(_) => Err(anyhow!(
+            "config key {} is expected to be a positive number",
+            key
+        )),
+    }
+}
+
+// the first locale is the one of the root of the website
+fn locale_output_dir(output: &Path, index: usize, locale: Option<&FileLocale>) -> PathBuf {
+    match locale {
+        Some(locale) if index > 0 => output.join(locale.path_segment()),
+        _ => output.to_path_buf(),
+    }
+}
+
+fn write_file<P: AsRef<Path>>(path: P, content: &str) -> Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content).with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use crate::test_util::TempDir;
+
+    fn output_override(output: &Path) -> (String, Value) {
+        (
+            String::from("output"),
+            Value::from(output.to_str().unwrap()),
+        )
+    }
+
+    #[test]
+    fn test_build() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("examples")
+            .join("basic")
+            .join("one_page_custom_layout");
+        let output = TempDir::new();
+        let output = output.path();
+
+        build(&root, &[output_override(output)]).unwrap();
+        let index = fs::read_to_string(output.join("index.html")).unwrap();
+        assert!(index.contains("a website with a custom layout built using TSG"));
+        assert!(index.contains("This is an example introduction"));
+        assert!(output.join("main.css").exists());
+    }
+
+    #[test]
+    fn test_build_localized() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("examples")
+            .join("l18n")
+            .join("basic");
+        let output = TempDir::new();
+        let output = output.path();
+
+        build(&root, &[output_override(output)]).unwrap();
+        let index = fs::read_to_string(output.join("index.html")).unwrap();
+        assert!(index.contains("A website built using"));
+        let index = fs::read_to_string(output.join("nl").join("index.html")).unwrap();
+        assert!(index.contains("Nederlandstalige lezers"));
+        assert!(index.contains("Een website gemaakt met behulp van"));
+        assert!(index.contains(r#"<html lang="nl">"#));
+        assert!(index.contains(r#"<link rel="alternate" hreflang="x-default" href="/index.html">"#));
+        assert!(index.contains(r#"<link rel="alternate" hreflang="nl" href="/nl/index.html">"#));
+        fs::remove_dir_all(output).unwrap();
+
+        let overrides = vec![
+            output_override(output),
+            (String::from("default_locale"), Value::from("nl")),
+            (
+                String::from("base_url"),
+                Value::from("https://example.com/"),
+            ),
+        ];
+        build(&root, &overrides).unwrap();
+        let index = fs::read_to_string(output.join("index.html")).unwrap();
+        assert!(index.contains("Een website gemaakt met behulp van"));
+        assert!(index.contains(
+            r#"<link rel="alternate" hreflang="x-default" href="https://example.com/index.html">"#
+        ));
+        assert!(index.contains(
+            r#"<link rel="alternate" hreflang="nl" href="https://example.com/index.html">"#
+        ));
+        assert!(!output.join("nl").exists());
+    }
+
+    #[test]
+    fn test_build_generated_locales() {
+        let dir = TempDir::new();
+        dir.write("pages/index.html", "hello");
+        dir.write("pages/index.nl.html", "hallo");
+        dir.write(
+            "pages/news.rhai",
+            r#"
+            fn generate(generator) {
+                for locale in generator.locales {
+                    generator.locale = locale;
+                    generator.html("news", `news for ${tsg.locale}`);
+                }
+                generator.locale = "nl";
+                generator.html("nieuws", "alleen nieuws");
+            }
+            "#,
+        );
+        let output = dir.path().join("public");
+
+        build(dir.path(), &[]).unwrap();
+        let read =
+            |path: &[&str]| fs::read_to_string(path.iter().fold(output.clone(), |p, s| p.join(s)));
+        assert_eq!(read(&["news.html"]).unwrap(), "news for ");
+        assert_eq!(read(&["nl", "news.html"]).unwrap(), "news for nl");
+        assert_eq!(read(&["nl", "nieuws.html"]).unwrap(), "alleen nieuws");
+        assert!(read(&["nieuws.html"]).is_err());
+
+        dir.write(
+            "pages/news.rhai",
+            r#"fn generate(generator) { generator.locale = "fr"; }"#,
+        );
+        let err = build(dir.path(), &[]).unwrap_err();
+        assert!(format!("{:#}", err).contains("unknown locale fr"));
+    }
+
+    #[test]
+    fn test_build_localized_assets() {
+        let dir = TempDir::new();
+        dir.write("pages/index.html", "hello");
+        dir.write("pages/index.nl.html", "hallo");
+        dir.write("assets/main.css", "default");
+        dir.write("assets/main.nl.css", "dutch");
+        dir.write("assets/img/logo.png", "logo");
+        dir.write("assets/x.min.js", "minified");
+        dir.write("assets/banner.nl.png", "banner");
+        dir.write("tsg.yml", "localized_assets: true\n");
+        let output = dir.path().join("public");
+
+        build(dir.path(), &[]).unwrap();
+        let read =
+            |path: &[&str]| fs::read_to_string(path.iter().fold(output.clone(), |p, s| p.join(s)));
+        assert_eq!(read(&["main.css"]).unwrap(), "default");
+        assert_eq!(read(&["img", "logo.png"]).unwrap(), "logo");
+        assert_eq!(read(&["nl", "main.css"]).unwrap(), "dutch");
+        assert_eq!(read(&["nl", "img", "logo.png"]).unwrap(), "logo");
+        assert!(read(&["main.nl.css"]).is_err());
+        // a suffix which is not a site locale is part of the name
+        assert_eq!(read(&["x.min.js"]).unwrap(), "minified");
+        assert_eq!(read(&["nl", "x.min.js"]).unwrap(), "minified");
+        // an asset without a variant for the locale is copied unchanged
+        assert_eq!(read(&["banner.nl.png"]).unwrap(), "banner");
+        assert_eq!(read(&["nl", "banner.png"]).unwrap(), "banner");
+    }
+}
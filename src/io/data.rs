@@ -1,45 +1,95 @@
 use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+
+use indexmap::IndexMap;
 
 use serde_json;
 use serde_yaml;
 
-use super::path::{PathComponent, PathIter};
+use super::path::{resolve_index, resolve_range, PathComponent, Predicate, PredicateOp};
+
+pub fn first_value<'a, 'b, P>(path: &'b P, values: &'a [Value]) -> Option<&'a Value>
+    where P: AsRef<[PathComponent<'b>]> + ?Sized
+{
+    let path = path.as_ref();
+    values.iter().find_map(|value| value.value(path))
+}
 
-pub fn first_value<'a, 'b, I>(path_it: I, values: &'a [Value]) -> Option<&'a Value>
-    where I: Into<PathIter<'b>>
+/// All values found for the path within the first of the given values which has any.
+pub fn first_values<'a, 'b, P>(path: &'b P, values: &'a [Value]) -> Vec<&'a Value>
+    where P: AsRef<[PathComponent<'b>]> + ?Sized
 {
-    let path: Vec<PathComponent<'b>> = path_it.into().collect();
+    let path = path.as_ref();
     for value in values {
-        if let Some(value) = value.value(PathIter::wrap(path.clone().into_iter())) {
-            return Some(value);
+        let found: Vec<&'a Value> = value.value_iter(path).collect();
+        if !found.is_empty() {
+            return found;
         }
     }
-    None
+    Vec::new()
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Null,
     String(String),
     Boolean(bool),
     Number(f64),
     Sequence(Vec<Value>),
-    Mapping(HashMap<String, Value>),
+    /// Mapping which keeps its keys in insertion order, e.g. the order in which they are defined in a file.
+    Mapping(IndexMap<String, Value>),
 }
 
 impl Value {
-    pub fn value<'a, 'b, T>(&'a self, t: T) -> Option<&'a Value>
+    pub fn value<'a, 'b, P>(&'a self, path: &'b P) -> Option<&'a Value>
+    where
+        P: AsRef<[PathComponent<'b>]> + ?Sized,
+    {
+        self.value_iter(path).next()
+    }
+
+    pub fn value_iter<'a, 'b, P>(&'a self, path: &'b P) -> ValueIter<'a, 'b>
     where
-        T: Into<PathIter<'b>>,
+        P: AsRef<[PathComponent<'b>]> + ?Sized,
     {
-        self.value_iter(t).next()
+        ValueIter::new(self, path.as_ref())
     }
 
-    pub fn value_iter<'a, 'b, T>(&'a self, t: T) -> ValueIter<'a, 'b>
+    /// Set the value found at the given path, creating any missing mappings along the way.
+    /// Returns false in case the path contains wildcards or leads through a primitive value.
+    pub fn set_value<'b, P>(&mut self, path: &P, value: Value) -> bool
     where
-        T: Into<PathIter<'b>>,
+        P: AsRef<[PathComponent<'b>]> + ?Sized,
     {
-        ValueIter::new(self, t)
+        let mut root = self;
+        for component in path.as_ref() {
+            let key = match component {
+                PathComponent::Name(name) => name.to_lowercase(),
+                PathComponent::Index(index) => index.to_string(),
+                PathComponent::Slice(..)
+                | PathComponent::Filter(_)
+                | PathComponent::Any
+                | PathComponent::AnyRecursive => return false,
+            };
+            root = match (root, component) {
+                (Value::Mapping(map), _) => {
+                    let index = match key_index(map, &key) {
+                        Some(index) => index,
+                        None => map.insert_full(key, Value::Mapping(IndexMap::new())).0,
+                    };
+                    &mut map[index]
+                }
+                (Value::Sequence(seq), PathComponent::Index(index)) => {
+                    match resolve_index(*index, seq.len()) {
+                        Some(index) => &mut seq[index],
+                        None => return false,
+                    }
+                }
+                _ => return false,
+            };
+        }
+        *root = value;
+        true
     }
 
     pub fn as_none(&self) -> Option<()> {
@@ -84,14 +134,14 @@ impl Value {
         }
     }
 
-    pub fn as_mapping(&self) -> Option<&HashMap<String, Value>> {
+    pub fn as_mapping(&self) -> Option<&IndexMap<String, Value>> {
         match self {
-            Value::Mapping(m) => Some(&m),
+            Value::Mapping(m) => Some(m),
             _ => None,
         }
     }
 
-    pub fn to_mapping(self) -> Option<HashMap<String, Value>> {
+    pub fn to_mapping(self) -> Option<IndexMap<String, Value>> {
         match self {
             Value::Mapping(m) => Some(m),
             _ => None,
@@ -226,7 +276,19 @@ where
     T: Into<Value>,
 {
     fn from(m: HashMap<String, T>) -> Value {
-        let m: HashMap<String, Value> = m.into_iter().map(|(k, v)| (k, v.into())).collect();
+        // a hash map has no order of its own, so its keys are sorted to keep the order deterministic
+        let mut m: IndexMap<String, Value> = m.into_iter().map(|(k, v)| (k, v.into())).collect();
+        m.sort_keys();
+        Value::Mapping(m)
+    }
+}
+
+impl<T> From<IndexMap<String, T>> for Value
+where
+    T: Into<Value>,
+{
+    fn from(m: IndexMap<String, T>) -> Value {
+        let m: IndexMap<String, Value> = m.into_iter().map(|(k, v)| (k, v.into())).collect();
         Value::Mapping(m)
     }
 }
@@ -237,10 +299,10 @@ impl From<serde_yaml::Value> for Value {
             serde_yaml::Value::Null => Value::Null,
             serde_yaml::Value::String(s) => Value::String(s),
             serde_yaml::Value::Bool(b) => Value::Boolean(b),
-            serde_yaml::Value::Number(n) => Value::Number(n.as_f64().unwrap_or(std::f64::NAN)),
+            serde_yaml::Value::Number(n) => Value::Number(n.as_f64().unwrap_or(f64::NAN)),
             serde_yaml::Value::Sequence(s) => s.into(),
             serde_yaml::Value::Mapping(m) => {
-                let m: HashMap<String, Value> = m.into_iter()
+                let m: IndexMap<String, Value> = m.into_iter()
                     .filter(|(k, _)| match k {
                         // filter out complex keys as these can anyhow not be indexed nicely by TSG user
                         serde_yaml::Value::Sequence(_) | serde_yaml::Value::Mapping(_) => false,
@@ -265,35 +327,96 @@ impl From<serde_json::Value> for Value {
             serde_json::Value::Null => Value::Null,
             serde_json::Value::String(s) => Value::String(s),
             serde_json::Value::Bool(b) => Value::Boolean(b),
-            serde_json::Value::Number(n) => Value::Number(n.as_f64().unwrap_or(std::f64::NAN)),
+            serde_json::Value::Number(n) => Value::Number(n.as_f64().unwrap_or(f64::NAN)),
             serde_json::Value::Array(arr) => arr.into(),
             serde_json::Value::Object(o) => {
-                let m: HashMap<String, Value> = o.into_iter().map(|(k, v)| (k, v.into())).collect();
+                let m: IndexMap<String, Value> = o.into_iter().map(|(k, v)| (k, v.into())).collect();
+                Value::Mapping(m)
+            }
+        }
+    }
+}
+
+impl From<toml::Value> for Value {
+    fn from(v: toml::Value) -> Value {
+        match v {
+            toml::Value::String(s) => Value::String(s),
+            toml::Value::Integer(x) => Value::Number(x as f64),
+            toml::Value::Float(x) => Value::Number(x),
+            toml::Value::Boolean(b) => Value::Boolean(b),
+            // dates are kept in their RFC 3339 format, the same way as YAML and JSON dates
+            toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+            toml::Value::Array(arr) => arr.into(),
+            toml::Value::Table(t) => {
+                let m: IndexMap<String, Value> = t.into_iter().map(|(k, v)| (k, v.into())).collect();
                 Value::Mapping(m)
             }
         }
     }
 }
 
+// keys are matched regardless of their case, preferring a key which is lowercase already
+fn key_index(map: &IndexMap<String, Value>, lowercase_key: &str) -> Option<usize> {
+    map.get_index_of(lowercase_key)
+        .or_else(|| map.keys().position(|key| key.to_lowercase() == lowercase_key))
+}
+
+/// Whether the child of the value found at the key of the predicate matches it.
+/// Literals are compared against booleans and numbers by their value, e.g. `1.0` equals `1`.
+pub fn matches_predicate(value: &Value, predicate: &Predicate) -> bool {
+    let literal = predicate.literal.as_ref();
+    let child = value.value(&predicate.key);
+    match predicate.op {
+        PredicateOp::Eq => child.is_some_and(|child| equals_literal(child, literal)),
+        PredicateOp::NotEq => !child.is_some_and(|child| equals_literal(child, literal)),
+        PredicateOp::Contains => match child {
+            Some(Value::Sequence(seq)) => seq.iter().any(|value| equals_literal(value, literal)),
+            Some(Value::String(s)) => s.contains(literal),
+            Some(child) => equals_literal(child, literal),
+            None => false,
+        },
+    }
+}
+
+fn equals_literal(value: &Value, literal: &str) -> bool {
+    match value {
+        Value::Null => literal == "null",
+        Value::String(s) => s == literal,
+        Value::Boolean(b) => literal.parse::<bool>().is_ok_and(|literal| literal == *b),
+        Value::Number(x) => literal.parse::<f64>().is_ok_and(|literal| literal == *x),
+        Value::Sequence(_) | Value::Mapping(_) => false,
+    }
+}
+
 pub struct ValueIter<'a, 'b> {
     stack: VecDeque<ValueIterInner<'a, 'b>>,
 }
 
 struct ValueIterInner<'a, 'b> {
     root: &'a Value,
-    path: Vec<PathComponent<'b>>,
+    path: &'b [PathComponent<'b>],
     path_index: usize,
     recursive: bool,
 }
 
 impl<'a, 'b> ValueIter<'a, 'b> {
-    pub fn new<T>(value: &'a Value, t: T) -> ValueIter<'a, 'b>
-    where
-        T: Into<PathIter<'b>>,
-    {
-        let root_value_iter = ValueIterInner::new(value, t);
+    pub fn new(value: &'a Value, path: &'b [PathComponent<'b>]) -> ValueIter<'a, 'b> {
         let mut stack = VecDeque::with_capacity(1);
-        stack.push_front(root_value_iter);
+        stack.push_front(ValueIterInner::new(value, path));
+        ValueIter { stack }
+    }
+
+    /// Iterate over the values found for the path at any depth below the given value,
+    /// the same as for the path with `**` in front of it.
+    pub fn new_recursive(value: &'a Value, path: &'b [PathComponent<'b>]) -> ValueIter<'a, 'b> {
+        let mut stack = VecDeque::new();
+        let root = ValueIterInner::new(value, path);
+        match value {
+            Value::Sequence(_) | Value::Mapping(_) => root.push_children(&mut stack, 0, true),
+            Value::Null | Value::String(_) | Value::Boolean(_) | Value::Number(_) => {
+                stack.push_back(ValueIterInner { recursive: true, ..root })
+            }
+        }
         ValueIter { stack }
     }
 }
@@ -308,7 +431,7 @@ impl<'a, 'b> Iterator for ValueIter<'a, 'b> {
                 return None;
             }
             let result = self.stack[0].next_value(&mut inner_stack);
-            if inner_stack.len() > 0 {
+            if !inner_stack.is_empty() {
                 self.stack.append(&mut inner_stack);
             }
             match result {
@@ -323,77 +446,41 @@ impl<'a, 'b> Iterator for ValueIter<'a, 'b> {
 }
 
 impl<'a, 'b> ValueIterInner<'a, 'b> {
-    pub fn new<T>(value: &'a Value, t: T) -> ValueIterInner<'a, 'b>
-    where
-        T: Into<PathIter<'b>>,
-    {
+    pub fn new(value: &'a Value, path: &'b [PathComponent<'b>]) -> ValueIterInner<'a, 'b> {
         ValueIterInner {
             root: value,
-            path: t.into().collect(),
+            path,
             path_index: 0,
             recursive: false,
         }
     }
 
     fn next_value(&mut self, stack: &mut VecDeque<ValueIterInner<'a, 'b>>) -> Option<&'a Value> {
-        while self.path_index < self.path.len() {
-            match self.path[self.path_index] {
-                PathComponent::Name(name) => {
-                    let opt_value = match self.root {
-                        Value::Null | Value::String(_) | Value::Boolean(_) | Value::Number(_) => {
-                            None
+        let path = self.path;
+        while self.path_index < path.len() {
+            let component = &path[self.path_index];
+            // a component following `**` can also match at any depth below the current root
+            let is_any = matches!(component, PathComponent::Any | PathComponent::AnyRecursive);
+            if self.recursive && !is_any {
+                self.push_children(stack, self.path_index, true);
+            }
+            match component {
+                PathComponent::Name(_) | PathComponent::Index(_) => {
+                    let opt_value = match (self.root, component) {
+                        (Value::Sequence(seq), PathComponent::Index(index)) => {
+                            resolve_index(*index, seq.len()).map(|index| &seq[index])
                         }
-                        Value::Sequence(seq) => name
-                            .parse::<usize>()
-                            .ok()
-                            .and_then(|index| {
-                                if index >= seq.len() {
-                                    return None;
-                                }
-                                if self.recursive {
-                                    for (value_index, value) in seq.iter().enumerate() {
-                                        if index == value_index {
-                                            continue;
-                                        }
-                                        stack.push_back(ValueIterInner {
-                                            root: value,
-                                            path: self.path[self.path_index..].to_vec(),
-                                            path_index: 0,
-                                            recursive: true,
-                                        });
-                                    }
-                                }
-                                Some(&seq[index])
+                        (Value::Mapping(map), PathComponent::Name(name)) => {
+                            key_index(map, &name.to_lowercase()).map(|index| &map[index])
+                        }
+                        // a property named after the index takes precedence over its position
+                        (Value::Mapping(map), PathComponent::Index(index)) => {
+                            map.get(&index.to_string()).or_else(|| {
+                                let index = resolve_index(*index, map.len())?;
+                                map.get_index(index).map(|(_, value)| value)
                             })
-                            .or_else(|| {
-                                for value in seq {
-                                    stack.push_back(ValueIterInner {
-                                        root: value,
-                                        path: self.path[self.path_index..].to_vec(),
-                                        path_index: 0,
-                                        recursive: true,
-                                    });
-                                }
-                                None
-                            }),
-                        Value::Mapping(map) => {
-                            let name = name.to_lowercase();
-                            let result = map.get(&name);
-                            if self.recursive {
-                                for (key, value) in map {
-                                    if result.is_some() && key == &name {
-                                        continue;
-                                    }
-                                    stack.push_back(ValueIterInner {
-                                        root: value,
-                                        path: self.path[self.path_index..].to_vec(),
-                                        path_index: 0,
-                                        recursive: true,
-                                    });
-                                }
-                            }
-                            result
                         }
+                        _ => None,
                     };
                     match opt_value {
                         Some(value) => {
@@ -405,6 +492,24 @@ impl<'a, 'b> ValueIterInner<'a, 'b> {
                         None => return None,
                     }
                 }
+                PathComponent::Filter(predicate) => {
+                    if !matches_predicate(self.root, predicate) {
+                        return None;
+                    }
+                    self.path_index += 1;
+                    self.recursive = false;
+                }
+                PathComponent::Slice(start, end) => {
+                    let len = match self.root {
+                        Value::Sequence(seq) => seq.len(),
+                        Value::Mapping(map) => map.len(),
+                        _ => return None,
+                    };
+                    let range = resolve_range(*start, *end, len);
+                    self.push_children_in(stack, self.path_index + 1, false, range);
+                    self.path_index = self.path.len() + 1;
+                    return None;
+                }
                 // no need to take into account recursive-ness when at an "any" path,
                 // as this is not possible due to the normalization process applied on a map prior to using it in ValueIter
                 PathComponent::Any => match self.root {
@@ -413,28 +518,9 @@ impl<'a, 'b> ValueIterInner<'a, 'b> {
                         self.path_index += 1;
                         continue;
                     }
-                    Value::Sequence(seq) => {
-                        for value in seq {
-                            stack.push_back(ValueIterInner {
-                                root: value,
-                                path: self.path[self.path_index + 1..].to_vec(),
-                                path_index: 0,
-                                recursive: false,
-                            });
-                        }
-                        self.path_index = self.path.len();
-                        return None;
-                    }
-                    Value::Mapping(map) => {
-                        for value in map.values() {
-                            stack.push_back(ValueIterInner {
-                                root: value,
-                                path: self.path[self.path_index + 1..].to_vec(),
-                                path_index: 0,
-                                recursive: false,
-                            });
-                        }
-                        self.path_index = self.path.len();
+                    Value::Sequence(_) | Value::Mapping(_) => {
+                        self.push_children(stack, self.path_index + 1, false);
+                        self.path_index = self.path.len() + 1;
                         return None;
                     }
                 },
@@ -447,34 +533,56 @@ impl<'a, 'b> ValueIterInner<'a, 'b> {
                         self.recursive = true;
                         continue;
                     }
-                    Value::Sequence(seq) => {
-                        for value in seq {
-                            stack.push_back(ValueIterInner {
-                                root: value,
-                                path: self.path[self.path_index + 1..].to_vec(),
-                                path_index: 0,
-                                recursive: true,
-                            });
-                        }
-                        self.path_index = self.path.len();
-                        return None;
-                    }
-                    Value::Mapping(map) => {
-                        for value in map.values() {
-                            stack.push_back(ValueIterInner {
-                                root: value,
-                                path: self.path[self.path_index + 1..].to_vec(),
-                                path_index: 0,
-                                recursive: true,
-                            });
-                        }
-                        self.path_index = self.path.len();
+                    Value::Sequence(_) | Value::Mapping(_) => {
+                        self.push_children(stack, self.path_index + 1, true);
+                        self.path_index = self.path.len() + 1;
                         return None;
                     }
                 },
             }
         }
 
-        None
+        if self.path_index > self.path.len() {
+            return None;
+        }
+        // the path is consumed, so the root is a match
+        if self.recursive {
+            self.push_children(stack, self.path_index, true);
+        }
+        self.path_index += 1;
+        Some(self.root)
+    }
+
+    fn push_children(
+        &self,
+        stack: &mut VecDeque<ValueIterInner<'a, 'b>>,
+        path_index: usize,
+        recursive: bool,
+    ) {
+        self.push_children_in(stack, path_index, recursive, 0..usize::MAX)
+    }
+
+    // only the children positioned within the given range are pushed
+    fn push_children_in(
+        &self,
+        stack: &mut VecDeque<ValueIterInner<'a, 'b>>,
+        path_index: usize,
+        recursive: bool,
+        range: Range<usize>,
+    ) {
+        let children: Box<dyn Iterator<Item = &'a Value>> = match self.root {
+            Value::Sequence(seq) => Box::new(seq.iter()),
+            Value::Mapping(map) => Box::new(map.values()),
+            _ => return,
+        };
+        let children = children.skip(range.start).take(range.end - range.start);
+        for value in children {
+            stack.push_back(ValueIterInner {
+                root: value,
+                path: self.path,
+                path_index,
+                recursive,
+            });
+        }
     }
 }
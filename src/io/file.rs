@@ -4,6 +4,7 @@ use std::error::Error;
 use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use anyhow::{anyhow, Result};
 use regex::Regex;
@@ -15,35 +16,47 @@ pub enum FileKind {
     Include,
     Layout,
     Page,
+    Data,
 }
 
-impl FileKind {
-    pub fn from_str(s: &str) -> Result<FileKind> {
+impl FromStr for FileKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<FileKind> {
         Ok(match s.to_lowercase().as_str() {
             "includes" => FileKind::Include,
             "layouts" => FileKind::Layout,
             "pages" => FileKind::Page,
+            "data" => FileKind::Data,
             kind => return Err(anyhow!("unexpected raw kind {}", kind)),
         })
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum FileFormat {
     Html,
     Markdown,
     Yaml,
     Json,
+    Toml,
+    Csv,
+    Tsv,
     Rhai,
     Bash,
 }
 
-impl FileFormat {
-    pub fn from_str(s: &str) -> std::result::Result<FileFormat, FileInfoError> {
+impl FromStr for FileFormat {
+    type Err = FileInfoError;
+
+    fn from_str(s: &str) -> std::result::Result<FileFormat, FileInfoError> {
         Ok(match s.to_lowercase().as_str() {
             "html" | "htm" | "xhtml" | "xml" => FileFormat::Html,
             "yaml" | "yml" => FileFormat::Yaml,
             "json" => FileFormat::Json,
+            "toml" => FileFormat::Toml,
+            "csv" => FileFormat::Csv,
+            "tsv" => FileFormat::Tsv,
             "rhai" => FileFormat::Rhai,
             "md" | "markdown" | "mdown" | "mkdn" | "mdwn" | "mdtxt" | "mdtext" | "text" | "rmd" => {
                 FileFormat::Markdown
@@ -54,15 +67,167 @@ impl FileFormat {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Locale of a localized file, defined as the suffix between its name and extension,
+/// e.g. `nl` for `footer.nl.html`, `en.gb` for `strings.en.gb.yml` and `accessible` for `main.accessible.css`.
+///
+/// A locale which starts with a language (e.g. `en`) is parsed as a BCP 47 language tag
+/// with an optional region (e.g. `GB`), while any other subtag is kept as a free-form variant.
+/// Subtags can be separated by a `.`, `-` or `_`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct FileLocale {
-    raw_str: String,
+    language: Option<String>,
+    region: Option<String>,
+    variants: Vec<String>,
 }
 
 impl FileLocale {
-    pub fn from_str(s: &str) -> FileLocale {
-        FileLocale {
-            raw_str: String::from(s),
+    /// Language subtag in lowercase, e.g. `en`.
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// Region subtag in uppercase, e.g. `GB`.
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    /// Variant subtags in lowercase, e.g. `accessible`.
+    pub fn variants(&self) -> &[String] {
+        &self.variants[..]
+    }
+
+    /// The locale in its POSIX form, e.g. `en_GB`.
+    pub fn to_posix(&self) -> String {
+        self.subtags().collect::<Vec<&str>>().join("_")
+    }
+
+    /// The locale as used for a directory or URL path segment, e.g. `en-gb`.
+    pub fn path_segment(&self) -> String {
+        self.to_string().to_lowercase()
+    }
+
+    /// The locale this locale falls back to, by dropping its last subtag,
+    /// e.g. `en-GB` for `en-GB-accessible` and `en` for `en-GB`.
+    pub fn parent(&self) -> Option<FileLocale> {
+        let mut parent = self.clone();
+        if parent.variants.pop().is_none()
+            && parent.region.take().is_none()
+            && parent.language.take().is_none()
+        {
+            return None;
+        }
+        if parent.language.is_none() && parent.variants.is_empty() {
+            return None;
+        }
+        Some(parent)
+    }
+
+    /// This locale followed by all locales it falls back to, from the most to the least specific.
+    /// A localized locale with variants falls back to its variants last,
+    /// e.g. `en-GB-accessible`, `en-GB`, `en` and `accessible`.
+    /// A file is resolved by trying these locales in order, ending with the unlocalized file.
+    pub fn fallbacks(&self) -> Vec<FileLocale> {
+        let mut locales = vec![self.clone()];
+        while let Some(parent) = locales.last().unwrap().parent() {
+            locales.push(parent);
+        }
+        if self.language.is_some() && !self.variants.is_empty() {
+            let mut locale = Some(FileLocale {
+                language: None,
+                region: None,
+                variants: self.variants.clone(),
+            });
+            while let Some(variant) = locale {
+                locale = variant.parent();
+                locales.push(variant);
+            }
+        }
+        locales
+    }
+
+    fn subtags(&self) -> impl Iterator<Item = &str> {
+        self.language
+            .iter()
+            .chain(self.region.iter())
+            .chain(self.variants.iter())
+            .map(String::as_str)
+    }
+}
+
+impl fmt::Display for FileLocale {
+    /// Format the locale as a BCP 47 language tag, e.g. `en-GB`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.subtags().collect::<Vec<&str>>().join("-"))
+    }
+}
+
+impl FromStr for FileLocale {
+    type Err = FileLocaleError;
+
+    fn from_str(s: &str) -> std::result::Result<FileLocale, Self::Err> {
+        let s = s.strip_prefix('.').unwrap_or(s);
+        if s.is_empty() {
+            return Err(FileLocaleError::Empty);
+        }
+        let mut subtags = s.split(['.', '-', '_']).peekable();
+        let mut locale = FileLocale {
+            language: None,
+            region: None,
+            variants: Vec::new(),
+        };
+        if let Some(language) = subtags.next_if(|subtag| is_language_subtag(subtag)) {
+            locale.language = Some(language.to_ascii_lowercase());
+            if let Some(region) = subtags.next_if(|subtag| is_region_subtag(subtag)) {
+                locale.region = Some(region.to_ascii_uppercase());
+            }
+        }
+        // a variant starts with a letter, such that e.g. the `0` of `release-1.0.md` is not one
+        for subtag in subtags {
+            if !subtag.starts_with(|c: char| c.is_ascii_alphabetic())
+                || !subtag.chars().all(|c| c.is_ascii_alphanumeric())
+            {
+                return Err(FileLocaleError::InvalidSubtag(String::from(s), String::from(subtag)));
+            }
+            let variant = subtag.to_ascii_lowercase();
+            if locale.variants.contains(&variant) {
+                return Err(FileLocaleError::DuplicateVariant(String::from(s), variant));
+            }
+            locale.variants.push(variant);
+        }
+        Ok(locale)
+    }
+}
+
+// a two or three letter ISO 639 language code, such as `en` or `nld`
+fn is_language_subtag(subtag: &str) -> bool {
+    (2..=3).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+// a two letter ISO 3166-1 country code or a three digit UN M.49 region code, such as `GB` or `419`
+fn is_region_subtag(subtag: &str) -> bool {
+    (subtag.len() == 2 && subtag.chars().all(|c| c.is_ascii_alphabetic()))
+        || (subtag.len() == 3 && subtag.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[derive(Debug)]
+pub enum FileLocaleError {
+    Empty,
+    InvalidSubtag(String, String),
+    DuplicateVariant(String, String),
+}
+
+impl Error for FileLocaleError {}
+
+impl fmt::Display for FileLocaleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileLocaleError::Empty => write!(f, "empty locale"),
+            FileLocaleError::InvalidSubtag(locale, subtag) => {
+                write!(f, "invalid subtag '{}' in locale {}", subtag, locale)
+            }
+            FileLocaleError::DuplicateVariant(locale, variant) => {
+                write!(f, "duplicate variant '{}' in locale {}", variant, locale)
+            }
         }
     }
 }
@@ -79,12 +244,18 @@ pub struct FileInfo {
 
 impl FileInfo {
     pub fn new(raw_path: &str) -> std::result::Result<FileInfo, FileInfoError> {
+        FileInfo::new_at(raw_path, 0)
+    }
+
+    /// Parse a path with its kind directory (e.g. `pages`) found at or after the given byte offset,
+    /// such that the directories leading up to the workspace root are not mistaken for it.
+    pub fn new_at(raw_path: &str, offset: usize) -> std::result::Result<FileInfo, FileInfoError> {
         lazy_static! {
-            static ref RE: Regex = Regex::new(r"(?i)(?P<kind>includes|layouts|pages)(?P<dir>((/|\\)[^/\\]+)+)?(/|\\)(?P<name>\s+)(?P<locale>(\.[a-z\-_\d]+)+)?(\.(?P<ext>[a-z]+)$").unwrap();
+            static ref RE: Regex = Regex::new(r"(?i)(^|/|\\)(?P<kind>includes|layouts|pages|data)(?P<dir>((/|\\)[^/\\]+)+)?(/|\\)(?P<name>[^/\\.]+)(?P<locale>(\.[a-z\-_\d]+)+)?(\.(?P<ext>[a-z]+))$").unwrap();
         }
         // extract raw name, locale (opt) and extension (indicates file format)
         let (raw_kind, raw_dir, raw_name, raw_locale_opt, raw_ext, path) =
-            match RE.captures(raw_path) {
+            match RE.captures_at(raw_path, offset) {
                 Some(m) => (
                     m.name("kind").unwrap(),
                     m.name("dir"), // dir is optional, and not defined if direct in root of kind
@@ -96,22 +267,33 @@ impl FileInfo {
                 None => return Err(FileInfoError::UnexpectedFilePath(String::from(raw_path))),
             };
         // "parse" the file format from the file extension
-        let file_format = FileFormat::from_str(raw_ext.as_str())?;
-        // optionally "parse" the locale from the locale part
-        let locale = raw_locale_opt.and_then(|m| Some(FileLocale::from_str(m.as_str())));
+        let file_format = raw_ext.as_str().parse()?;
+        // optionally "parse" the locale from the locale part, where the dotted parts
+        // before a valid locale belong to the name instead, e.g. `release-1.0.en.md`
+        let (name, locale) = match raw_locale_opt {
+            Some(m) => m
+                .as_str()
+                .match_indices('.')
+                .find_map(|(i, _)| {
+                    let locale = m.as_str()[i..].parse().ok()?;
+                    Some((raw_name.start()..m.start() + i, Some(locale)))
+                })
+                .unwrap_or((raw_name.start()..m.end(), None)),
+            None => (raw_name.range(), None),
+        };
         // "parse" the kind dir from file path, no need to do fancy here as the
         // regex above should have ensured it is one of our expected kinds
-        let kind = FileKind::from_str(raw_kind.as_str()).unwrap();
+        let kind = raw_kind.as_str().parse().unwrap();
         // optionally turn the dir into a String
-        let directory = raw_dir.and_then(|dir| Some(dir.range()));
+        let directory = raw_dir.map(|dir| dir.range());
 
         // return the parsed File Info
         Ok(FileInfo {
-            kind: kind,
-            path: path,
-            directory: directory,
-            name: raw_name.range(),
-            locale: locale,
+            kind,
+            path,
+            directory,
+            name,
+            locale,
             format: file_format,
         })
     }
@@ -127,7 +309,7 @@ impl FileInfo {
     pub fn directory(&self) -> Option<&str> {
         self.directory
             .as_ref()
-            .and_then(|range| Some(&self.path[range.start..range.end]))
+            .map(|range| &self.path[range.start..range.end])
     }
 
     pub fn name(&self) -> &str {
@@ -154,6 +336,24 @@ impl TryFrom<&Path> for FileInfo {
     }
 }
 
+impl TryFrom<(&Path, &Path)> for FileInfo {
+    type Error = FileInfoError;
+
+    /// Parse a path found within the given workspace root, with its kind
+    /// relative to that root (see [`FileInfo::new_at`]).
+    fn try_from((root, path): (&Path, &Path)) -> std::result::Result<FileInfo, FileInfoError> {
+        let relative_path = path.strip_prefix(root).map_err(|_| FileInfoError::InvalidPath)?;
+        match (path.to_str(), relative_path.to_str()) {
+            // offset of the separator in front of the kind directory
+            (Some(path_str), Some(relative_path_str)) => FileInfo::new_at(
+                path_str,
+                path_str.len().saturating_sub(relative_path_str.len() + 1),
+            ),
+            _ => Err(FileInfoError::InvalidPath),
+        }
+    }
+}
+
 impl TryFrom<&PathBuf> for FileInfo {
     type Error = FileInfoError;
 
@@ -186,6 +386,7 @@ impl fmt::Display for FileInfoError {
     }
 }
 
+#[derive(Clone)]
 pub struct File {
     file_info: FileInfo,
     meta: Option<Meta>,
@@ -193,9 +394,9 @@ pub struct File {
 }
 
 impl File {
-    pub fn read<P: AsRef<Path>>(path: P) -> Result<File> {
-        let path = path.as_ref();
-        let file_info: FileInfo = path.try_into()?;
+    /// Read the file at the given path within the workspace found at the given root.
+    pub fn read<P: AsRef<Path>, Q: AsRef<Path>>(root: P, path: Q) -> Result<File> {
+        let file_info: FileInfo = (root.as_ref(), path.as_ref()).try_into()?;
         file_info.try_into()
     }
 
@@ -207,6 +408,11 @@ impl File {
         self.meta.as_ref()
     }
 
+    /// Metadata of the File, which is created empty in case the File had none.
+    pub fn meta_mut(&mut self) -> &mut Meta {
+        self.meta.get_or_insert_with(Meta::default)
+    }
+
     pub fn content(&self) -> &[u8] {
         &self.content[..]
     }
@@ -221,3 +427,67 @@ impl TryFrom<FileInfo> for File {
         Ok(File { file_info, meta, content })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_locale() {
+        let test_cases = vec![
+            (".nl", "nl", "nl", "nl", vec!["nl"]),
+            (".en.gb", "en-GB", "en_GB", "en-gb", vec!["en-GB", "en"]),
+            ("en_GB", "en-GB", "en_GB", "en-gb", vec!["en-GB", "en"]),
+            ("es-419", "es-419", "es_419", "es-419", vec!["es-419", "es"]),
+            ("accessible", "accessible", "accessible", "accessible", vec!["accessible"]),
+            (
+                ".en.GB.accessible",
+                "en-GB-accessible",
+                "en_GB_accessible",
+                "en-gb-accessible",
+                vec!["en-GB-accessible", "en-GB", "en", "accessible"],
+            ),
+        ];
+        for (input, tag, posix, path_segment, fallbacks) in test_cases {
+            let locale: FileLocale = input.parse().unwrap();
+            assert_eq!(locale.to_string(), tag);
+            assert_eq!(locale.to_posix(), posix);
+            assert_eq!(locale.path_segment(), path_segment);
+            let locales: Vec<String> = locale.fallbacks().iter().map(|l| l.to_string()).collect();
+            assert_eq!(locales, fallbacks);
+        }
+
+        for input in ["", ".", "en..gb", "en.g!", "en.gb.dark.dark", "en.1"] {
+            assert!(input.parse::<FileLocale>().is_err(), "{}", input);
+        }
+
+        let en: FileLocale = "en".parse().unwrap();
+        let en_gb: FileLocale = "en.gb".parse().unwrap();
+        assert_eq!(en_gb, "EN-gb".parse().unwrap());
+        assert!(en < en_gb);
+    }
+
+    #[test]
+    fn test_file_info() {
+        let test_cases = vec![
+            ("pages/index.md", "index", None),
+            ("pages/index.en.gb.md", "index", Some("en-GB")),
+            ("pages/blog/release-1.0.md", "release-1.0", None),
+            ("pages/blog/release-1.0.nl.md", "release-1.0", Some("nl")),
+        ];
+        for (input, name, locale) in test_cases {
+            let info = FileInfo::new(input).unwrap();
+            assert_eq!(info.name(), name, "{}", input);
+            assert_eq!(info.locale().map(|l| l.to_string()), locale.map(String::from));
+        }
+
+        // the kind is found relative to the workspace root, which can be named after a kind
+        let root = Path::new("sites").join("data");
+        let path = root.join("pages").join("docs").join("index.md");
+        let info = FileInfo::try_from((root.as_path(), path.as_path())).unwrap();
+        assert!(matches!(info.kind(), FileKind::Page));
+        assert_eq!(info.directory(), Some(&*format!("{}docs", std::path::MAIN_SEPARATOR)));
+        // a kind is a directory of its own
+        assert!(FileInfo::new("mypages/index.md").is_err());
+    }
+}
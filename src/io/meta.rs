@@ -1,73 +1,152 @@
-use std::collections::HashMap;
-
 use anyhow::Result;
+use indexmap::IndexMap;
 use regex::bytes::Regex;
 use serde_yaml;
 
 use super::{Value, ValueIter};
-use super::path::PathIter;
+use super::path::PathComponent;
 
+#[derive(Debug, Clone)]
 pub struct Meta {
     content: Value,
 }
 
 use super::file::FileFormat;
 
+impl Default for Meta {
+    fn default() -> Meta {
+        Meta {
+            content: Value::Mapping(IndexMap::new()),
+        }
+    }
+}
+
 impl Meta {
     pub fn extract(format: FileFormat, content: &mut Vec<u8>) -> Result<Option<Meta>> {
         match format {
             // extract as header data
             FileFormat::Markdown => Meta::extract_markdown(content),
             FileFormat::Html => Meta::extract_html(content),
+            FileFormat::Rhai => Meta::extract_script(content),
             // extract from entire file data
             FileFormat::Yaml => Meta::extract_yaml(content),
             FileFormat::Json => Meta::extract_json(content),
+            FileFormat::Toml => Meta::extract_toml(content),
+            FileFormat::Csv => Meta::extract_csv(content, b','),
+            FileFormat::Tsv => Meta::extract_csv(content, b'\t'),
             // other file formats do not support Meta data, and thus we can immediately return None
-            FileFormat::Rhai | FileFormat::Bash => Ok(None),
+            FileFormat::Bash => Ok(None),
         }
     }
 
-    pub fn as_value<'a>(&'a self) -> &'a Value {
+    pub fn as_value(&self) -> &Value {
         &self.content
     }
 
-    pub fn value<'a, 'b, T>(&'a self, t: T) -> Option<&'a Value>
+    pub fn set_value<'b, P>(&mut self, path: &P, value: Value) -> bool
     where
-        T: Into<PathIter<'b>>,
+        P: AsRef<[PathComponent<'b>]> + ?Sized,
     {
-        self.content.value(t)
+        self.content.set_value(path, value)
     }
 
-    pub fn value_iter<'a, 'b, T>(&'a self, t: T) -> ValueIter<'a, 'b>
+    pub fn value<'a, 'b, P>(&'a self, path: &'b P) -> Option<&'a Value>
     where
-        T: Into<PathIter<'b>>,
+        P: AsRef<[PathComponent<'b>]> + ?Sized,
     {
-        self.content.value_iter(t)
+        self.content.value(path)
+    }
+
+    pub fn value_iter<'a, 'b, P>(&'a self, path: &'b P) -> ValueIter<'a, 'b>
+    where
+        P: AsRef<[PathComponent<'b>]> + ?Sized,
+    {
+        self.content.value_iter(path)
     }
 
     fn extract_html(content: &mut Vec<u8>) -> Result<Option<Meta>> {
         lazy_static! {
             static ref RE: Regex = Regex::new(
-                r"(?m)^\s*<!--\s*[\n\r]+\s*(?P<meta>.+?)\s*[\n\r]+\s*-->\s*\n*(?P<next>.)?"
+                r"(?s)\A\s*<!--[ \t]*\r?\n(?P<meta>.+?)\r?\n\s*-->[ \t]*[\r\n]*"
             )
             .unwrap();
         }
-        Meta::extract_header(&RE, content)
+        if let Some(meta) = Meta::extract_header(&RE, content, parse_yaml_header)? {
+            return Ok(Some(meta));
+        }
+        match Meta::extract_fenced_header(content)? {
+            Some(meta) => Ok(Some(meta)),
+            None => Meta::extract_json_object_header(content),
+        }
     }
 
     fn extract_markdown(content: &mut Vec<u8>) -> Result<Option<Meta>> {
+        match Meta::extract_script(content)? {
+            Some(meta) => Ok(Some(meta)),
+            None => Meta::extract_json_object_header(content),
+        }
+    }
+
+    // a JSON object is not supported as a header of scripts, as it is ambiguous with a Rhai block
+    fn extract_script(content: &mut Vec<u8>) -> Result<Option<Meta>> {
         lazy_static! {
             static ref RE: Regex = Regex::new(
-                r"(?m)^\s*---\s*[\n\r]+\s*(?P<meta>.+?)\s*[\n\r]+\s*---\s*\n*(?P<next>.)?"
+                r"(?s)\A\s*---[ \t]*\r?\n(?P<meta>.+?)\r?\n\s*---[ \t]*[\r\n]*"
+            )
+            .unwrap();
+        }
+        match Meta::extract_header(&RE, content, parse_yaml_header)? {
+            Some(meta) => Ok(Some(meta)),
+            None => Meta::extract_fenced_header(content),
+        }
+    }
+
+    // TOML front matter, as used by Hugo, is fenced by `+++`, and JSON front matter by `;;;`
+    fn extract_fenced_header(content: &mut Vec<u8>) -> Result<Option<Meta>> {
+        lazy_static! {
+            static ref TOML_RE: Regex = Regex::new(
+                r"(?s)\A\s*\+\+\+[ \t]*\r?\n(?P<meta>.+?)\r?\n\s*\+\+\+[ \t]*[\r\n]*"
             )
             .unwrap();
+            static ref JSON_RE: Regex = Regex::new(
+                r"(?s)\A\s*;;;[ \t]*\r?\n(?P<meta>.+?)\r?\n\s*;;;[ \t]*[\r\n]*"
+            )
+            .unwrap();
+        }
+        match Meta::extract_header(&TOML_RE, content, parse_toml_header)? {
+            Some(meta) => Ok(Some(meta)),
+            None => Meta::extract_header(&JSON_RE, content, parse_json_header),
         }
-        Meta::extract_header(&RE, content)
+    }
+
+    // JSON front matter can also be an unfenced object, ending at a line break
+    fn extract_json_object_header(content: &mut Vec<u8>) -> Result<Option<Meta>> {
+        let start = match content.iter().position(|b| !b.is_ascii_whitespace()) {
+            Some(start) if content[start] == b'{' => start,
+            _ => return Ok(None),
+        };
+        let mut it = serde_json::Deserializer::from_slice(&content[start..])
+            .into_iter::<IndexMap<String, serde_json::Value>>();
+        // content which merely starts with a curly brace is not seen as front matter
+        let m = match it.next() {
+            Some(Ok(m)) => m,
+            _ => return Ok(None),
+        };
+        let mut end = start + it.byte_offset();
+        end += content[end..].iter().take_while(|b| matches!(b, b' ' | b'\t')).count();
+        if end < content.len() && !matches!(content[end], b'\r' | b'\n') {
+            return Ok(None);
+        }
+        end += content[end..].iter().take_while(|b| matches!(b, b'\r' | b'\n')).count();
+        drop_first_n_bytes(content, end);
+        Ok(Some(Meta {
+            content: Value::Mapping(m.into_iter().map(|(k, v)| (k, v.into())).collect()),
+        }))
     }
 
     fn extract_yaml(content: &mut Vec<u8>) -> Result<Option<Meta>> {
-        let m: HashMap<String, serde_yaml::Value> = serde_yaml::from_slice(&content)?;
-        let map: HashMap<String, Value> = m.into_iter().map(|(k, v)| (k, v.into())).collect();
+        let m: IndexMap<String, serde_yaml::Value> = serde_yaml::from_slice(content)?;
+        let map: IndexMap<String, Value> = m.into_iter().map(|(k, v)| (k, v.into())).collect();
         drop_first_n_bytes(content, content.len());
         Ok(Some(Meta {
             content: Value::Mapping(map),
@@ -75,34 +154,57 @@ impl Meta {
     }
 
     fn extract_json(content: &mut Vec<u8>) -> Result<Option<Meta>> {
-        let m: HashMap<String, serde_json::Value> = serde_json::from_slice(&content)?;
-        let map: HashMap<String, Value> = m.into_iter().map(|(k, v)| (k, v.into())).collect();
+        let m: IndexMap<String, serde_json::Value> = serde_json::from_slice(content)?;
+        let map: IndexMap<String, Value> = m.into_iter().map(|(k, v)| (k, v.into())).collect();
         drop_first_n_bytes(content, content.len());
         Ok(Some(Meta {
             content: Value::Mapping(map),
         }))
     }
 
-    fn extract_header(re: &Regex, content: &mut Vec<u8>) -> Result<Option<Meta>> {
-        let result = match re
+    fn extract_toml(content: &mut Vec<u8>) -> Result<Option<Meta>> {
+        let map = parse_toml_header(content)?;
+        drop_first_n_bytes(content, content.len());
+        Ok(Some(Meta {
+            content: Value::Mapping(map),
+        }))
+    }
+
+    // each row is a mapping keyed by the header line
+    fn extract_csv(content: &mut Vec<u8>, delimiter: u8) -> Result<Option<Meta>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .trim(csv::Trim::All)
+            .from_reader(content.as_slice());
+        let headers: Vec<String> = reader.headers()?.iter().map(|h| h.to_lowercase()).collect();
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let row: IndexMap<String, Value> = headers
+                .iter()
+                .cloned()
+                .zip(record?.iter().map(csv_field_value))
+                .collect();
+            rows.push(Value::Mapping(row));
+        }
+        drop_first_n_bytes(content, content.len());
+        Ok(Some(Meta {
+            content: Value::Sequence(rows),
+        }))
+    }
+
+    fn extract_header(
+        re: &Regex,
+        content: &mut Vec<u8>,
+        parse: fn(&[u8]) -> Result<IndexMap<String, Value>>,
+    ) -> Result<Option<Meta>> {
+        let result = re
             .captures(content)
-            .and_then(|m| m.name("meta").and_then(|meta| Some((m, meta))))
-        {
-            None => None,
-            Some((m, meta)) => Some((
-                meta.as_bytes().to_vec(),
-                m.name("next").and_then(|n| Some(n.start())),
-            )),
-        };
+            .and_then(|m| m.name("meta").map(|meta| (meta.as_bytes().to_vec(), m.get(0).unwrap().end())));
         match result {
             None => Ok(None),
-            Some((raw_content, n_opt)) => {
-                if let Some(n) = n_opt {
-                    drop_first_n_bytes(content, n);
-                }
-                let m: HashMap<String, serde_yaml::Value> = serde_yaml::from_slice(&raw_content)?;
-                let map: HashMap<String, Value> =
-                    m.into_iter().map(|(k, v)| (k, v.into())).collect();
+            Some((raw_content, n)) => {
+                drop_first_n_bytes(content, n);
+                let map = parse(&raw_content)?;
                 Ok(Some(Meta {
                     content: Value::Mapping(map),
                 }))
@@ -111,21 +213,289 @@ impl Meta {
     }
 }
 
+fn parse_yaml_header(raw_content: &[u8]) -> Result<IndexMap<String, Value>> {
+    let m: IndexMap<String, serde_yaml::Value> = serde_yaml::from_slice(raw_content)?;
+    Ok(m.into_iter().map(|(k, v)| (k, v.into())).collect())
+}
+
+fn parse_json_header(raw_content: &[u8]) -> Result<IndexMap<String, Value>> {
+    let m: IndexMap<String, serde_json::Value> = serde_json::from_slice(raw_content)?;
+    Ok(m.into_iter().map(|(k, v)| (k, v.into())).collect())
+}
+
+fn parse_toml_header(raw_content: &[u8]) -> Result<IndexMap<String, Value>> {
+    let m: IndexMap<String, toml::Value> = toml::from_slice(raw_content)?;
+    Ok(m.into_iter().map(|(k, v)| (k, v.into())).collect())
+}
+
+// CSV has no types, so numbers and booleans are recognized from their text,
+// except for numbers with leading zeros such as the zip code `01234`, which remain strings
+fn csv_field_value(field: &str) -> Value {
+    if field.eq_ignore_ascii_case("true") {
+        return Value::Boolean(true);
+    }
+    if field.eq_ignore_ascii_case("false") {
+        return Value::Boolean(false);
+    }
+    let digits = field.strip_prefix(['-', '+']).unwrap_or(field).as_bytes();
+    if digits.len() > 1 && digits[0] == b'0' && digits[1].is_ascii_digit() {
+        return Value::String(String::from(field));
+    }
+    match field.parse::<f64>() {
+        Ok(x) if x.is_finite() => Value::Number(x),
+        _ => Value::String(String::from(field)),
+    }
+}
+
 fn drop_first_n_bytes(vec: &mut Vec<u8>, n: usize) {
-    // We have to make sure vec has enough elements.
-    // You could make the function unsafe and ask the caller to ensure
-    // this condition.
-    assert!(vec.len() >= n);
-
-    unsafe {
-        // We need to update the vector. The values from index `i` to its end
-        // need to be moved at the beginning of the vector.
-        // SAFETY:
-        //  * We have an exclusive reference to the vector. It is both valid for reads and writes.
-        std::ptr::copy(vec.as_ptr().add(n), vec.as_mut_ptr(), n);
-
-        // And update the length of `vec`.
-        // SAFETY: This subtraction is safe because we previously checked that `vec.len() >= n`.
-        vec.set_len(vec.len() - n);
+    vec.drain(..n);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_util::parse_path;
+
+    #[test]
+    fn test_extract() {
+        let test_cases = vec![
+            (FileFormat::Markdown, "# Hello", None, "# Hello"),
+            (
+                FileFormat::Markdown,
+                "---\ntitle: Hello\n---\n# Hello",
+                Some(("title", Value::from("Hello"))),
+                "# Hello",
+            ),
+            (
+                FileFormat::Markdown,
+                "+++\ntitle = \"Hello\"\ndraft = true\n+++\n# Hello",
+                Some(("draft", Value::from(true))),
+                "# Hello",
+            ),
+            (
+                FileFormat::Html,
+                "+++\n[author]\nname = \"Glen\"\n+++\n<p>Hello</p>",
+                Some(("author.name", Value::from("Glen"))),
+                "<p>Hello</p>",
+            ),
+            (
+                FileFormat::Markdown,
+                ";;;\n{\"title\": \"Hello\", \"tags\": [\"rust\"]}\n;;;\n# Hello",
+                Some(("tags.0", Value::from("rust"))),
+                "# Hello",
+            ),
+            (
+                FileFormat::Markdown,
+                "\n{\n  \"title\": \"Hello\"\n}\n\n# Hello",
+                Some(("title", Value::from("Hello"))),
+                "# Hello",
+            ),
+            (
+                FileFormat::Html,
+                "{\"author\": {\"name\": \"Glen\"}}\n<p>Hello</p>",
+                Some(("author.name", Value::from("Glen"))),
+                "<p>Hello</p>",
+            ),
+            (FileFormat::Markdown, "{not json}\n# Hello", None, "{not json}\n# Hello"),
+            (FileFormat::Html, "{\"a\": 1} <p>Hello</p>", None, "{\"a\": 1} <p>Hello</p>"),
+            (FileFormat::Rhai, "{\"a\": 1}\n", None, "{\"a\": 1}\n"),
+            (
+                FileFormat::Toml,
+                "weight = 3\ndate = 2021-12-01\n[[links]]\nurl = \"https://rhai.rs\"",
+                Some(("links.0.url", Value::from("https://rhai.rs"))),
+                "",
+            ),
+            (
+                FileFormat::Toml,
+                "weight = 3\ndate = 2021-12-01",
+                Some(("date", Value::from("2021-12-01"))),
+                "",
+            ),
+            (
+                FileFormat::Csv,
+                "Name,Price,In Stock\nrust, 9.5 ,true\n\"tsg, rhai\",1e3,no",
+                Some(("1.name", Value::from("tsg, rhai"))),
+                "",
+            ),
+            (
+                FileFormat::Csv,
+                "Name,Price,In Stock\nrust, 9.5 ,true\n\"tsg, rhai\",1e3,no",
+                Some(("0.price", Value::from(9.5))),
+                "",
+            ),
+            (
+                FileFormat::Csv,
+                "Name,Price,In Stock\nrust, 9.5 ,true\n\"tsg, rhai\",1e3,no",
+                Some(("1.price", Value::from(1000))),
+                "",
+            ),
+            (
+                FileFormat::Csv,
+                "city,zip\nBrussels,1000\nHartford,06103",
+                Some(("1.zip", Value::from("06103"))),
+                "",
+            ),
+            (
+                FileFormat::Csv,
+                "city,zip\nBrussels,1000\nHartford,06103",
+                Some(("0.zip", Value::from(1000))),
+                "",
+            ),
+            (
+                FileFormat::Csv,
+                "name,price\nshirt,19.90\nmug,2.50\npen,1.0\ncap,-0.5\nid,007",
+                Some(("*.price", Value::from(19.9))),
+                "",
+            ),
+            (
+                FileFormat::Csv,
+                "name,price\nshirt,19.90\nmug,2.50\npen,1.0\ncap,-0.5\nid,007",
+                Some(("[1..].price", Value::from(2.5))),
+                "",
+            ),
+            (
+                FileFormat::Csv,
+                "name,price\nshirt,19.90\nmug,2.50\npen,1.0\ncap,-0.5\nid,007",
+                Some(("2.price", Value::from(1))),
+                "",
+            ),
+            (
+                FileFormat::Csv,
+                "name,price\nshirt,19.90\nmug,2.50\npen,1.0\ncap,-0.5\nid,007",
+                Some(("3.price", Value::from(-0.5))),
+                "",
+            ),
+            (
+                FileFormat::Csv,
+                "name,price\nshirt,19.90\nmug,2.50\npen,1.0\ncap,-0.5\nid,007",
+                Some(("4.price", Value::from("007"))),
+                "",
+            ),
+            (
+                FileFormat::Csv,
+                "name,price\nshirt,19.90\nmug,2.50\npen,1.0\ncap,-0.5\nid,007",
+                Some(("*[price=19.9].name", Value::from("shirt"))),
+                "",
+            ),
+            (
+                FileFormat::Tsv,
+                "name\tin stock\nrust\tTRUE\ntsg\tNaN",
+                Some(("0.in stock", Value::from(true))),
+                "",
+            ),
+            (
+                FileFormat::Tsv,
+                "name\tin stock\nrust\tTRUE\ntsg\tNaN",
+                Some(("1.in stock", Value::from("NaN"))),
+                "",
+            ),
+        ];
+        for (format, input, expected_value, expected_content) in test_cases {
+            let mut content = input.as_bytes().to_vec();
+            let meta = Meta::extract(format, &mut content).unwrap();
+            match expected_value {
+                None => assert!(meta.is_none()),
+                Some((path, value)) => {
+                    assert_eq!(meta.unwrap().value(&parse_path(path)), Some(&value))
+                }
+            }
+            assert_eq!(String::from_utf8(content).unwrap(), expected_content);
+        }
+    }
+
+    #[test]
+    fn test_extract_json_header() {
+        let test_cases = vec![
+            (
+                FileFormat::Html,
+                ";;;\r\n{\"draft\": false, \"weight\": 3, \"author\": null}\r\n;;;\r\n<p>Hello</p>",
+                vec![
+                    ("draft", Some(Value::from(false))),
+                    ("weight", Some(Value::from(3))),
+                    ("author", Some(Value::Null)),
+                ],
+                "<p>Hello</p>",
+            ),
+            (
+                FileFormat::Markdown,
+                "{\"title\": \"Hello\", \"tags\": [\"rust\", \"tsg\"]} \t\n# Hello\n{\"a\": 1}",
+                vec![
+                    ("title", Some(Value::from("Hello"))),
+                    ("tags.-1", Some(Value::from("tsg"))),
+                    ("a", None),
+                ],
+                "# Hello\n{\"a\": 1}",
+            ),
+            // a YAML header is still preferred over a JSON one
+            (
+                FileFormat::Markdown,
+                "---\ntitle: Hello\n---\n{\"title\": \"Bye\"}\n",
+                vec![("title", Some(Value::from("Hello")))],
+                "{\"title\": \"Bye\"}\n",
+            ),
+        ];
+        for (format, input, expected_values, expected_content) in test_cases {
+            let mut content = input.as_bytes().to_vec();
+            let meta = Meta::extract(format, &mut content).unwrap().unwrap();
+            for (path, value) in expected_values {
+                assert_eq!(meta.value(&parse_path(path)), value.as_ref(), "{}", path);
+            }
+            assert_eq!(String::from_utf8(content).unwrap(), expected_content);
+        }
+
+        // a fenced header has to be valid JSON, unlike an object that merely starts the content
+        let mut content = b";;;\n{title: Hello}\n;;;\n# Hello".to_vec();
+        assert!(Meta::extract(FileFormat::Markdown, &mut content).is_err());
+        let mut content = b"{title: Hello}\n# Hello".to_vec();
+        assert!(Meta::extract(FileFormat::Markdown, &mut content).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_extract_order() {
+        let test_cases = vec![
+            (FileFormat::Yaml, "zeta: 1\nalpha: 2\nmu: 3"),
+            (FileFormat::Json, r#"{"zeta": 1, "alpha": 2, "mu": 3}"#),
+            (FileFormat::Toml, "zeta = 1\nalpha = 2\nmu = 3"),
+            (FileFormat::Markdown, "---\nzeta: 1\nalpha: 2\nmu: 3\n---\n"),
+        ];
+        for (format, input) in test_cases {
+            let mut content = input.as_bytes().to_vec();
+            let meta = Meta::extract(format, &mut content).unwrap().unwrap();
+            let keys: Vec<&str> =
+                meta.as_value().as_mapping().unwrap().keys().map(String::as_str).collect();
+            assert_eq!(keys, vec!["zeta", "alpha", "mu"]);
+            let values: Vec<&Value> = meta.value_iter(&parse_path("*")).collect();
+            assert_eq!(values, vec![&Value::from(1), &Value::from(2), &Value::from(3)]);
+            // positions within a mapping follow the same order
+            assert_eq!(meta.value(&parse_path("0")), Some(&Value::from(1)));
+            assert_eq!(meta.value(&parse_path("-1")), Some(&Value::from(3)));
+            assert_eq!(meta.value(&parse_path("3")), None);
+        }
+
+        // a property named after a position takes precedence over that position
+        let mut content = b"2: two\nzeta: 1\nalpha: 2".to_vec();
+        let meta = Meta::extract(FileFormat::Yaml, &mut content).unwrap().unwrap();
+        assert_eq!(meta.value(&parse_path("2")), Some(&Value::from("two")));
+        assert_eq!(meta.value(&parse_path("-1")), Some(&Value::from(2)));
+    }
+
+    #[test]
+    fn test_value_recursive() {
+        let mut content = b"name: a\nauthor:\n  name: b\nposts:\n  - name: c\n  - title: d"
+            .to_vec();
+        let meta = Meta::extract(FileFormat::Yaml, &mut content).unwrap().unwrap();
+        // resolving a path recursively is the same as resolving it with `**` in front of it
+        for path in ["name", "[title=d]", "posts[1..]"] {
+            let path = parse_path(path);
+            let values: Vec<&Value> =
+                ValueIter::new_recursive(meta.as_value(), path.as_ref()).collect();
+            let expected: Vec<&Value> =
+                meta.value_iter(&parse_path(&format!("**.{}", path))).collect();
+            assert_eq!(values, expected, "{}", path);
+        }
+        let names: Vec<&Value> =
+            ValueIter::new_recursive(meta.as_value(), parse_path("name").as_ref()).collect();
+        assert_eq!(names, vec![&Value::from("b"), &Value::from("c")]);
     }
 }
@@ -2,7 +2,7 @@ pub mod data;
 pub use data::{Value, ValueIter};
 
 mod file;
-pub use file::{File, FileInfo, FileFormat, FileKind, FileLocale};
+pub use file::{File, FileInfo, FileFormat, FileKind, FileLocale, FileLocaleError};
 
 mod meta;
 pub use meta::Meta;
@@ -10,4 +10,4 @@ pub use meta::Meta;
 pub mod path;
 
 mod workspace;
-pub use workspace::{Workspace, FileOrValue};
+pub use workspace::{DataIter, FileOrValue, FileOrValueIter, LocalizedAsset, Workspace};
@@ -1,26 +1,258 @@
-#[derive(Debug, Clone, Copy, PartialEq)]
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum PathComponent<'a> {
-    Name(&'a str),
+    Name(Cow<'a, str>),
+    /// `posts.0` or `posts.-1`: a single element, counted from the end when negative
+    Index(isize),
+    /// `posts[2..5]` or `posts[..3]`: a range of elements, with its bounds counted
+    /// from the end when negative and defaulting to the start and end when omitted
+    Slice(Option<isize>, Option<isize>),
+    /// `posts.*[draft=false]`: the current file or value, if it matches the predicate
+    Filter(Predicate<'a>),
     Any,
     AnyRecursive,
 }
 
+impl<'a> PathComponent<'a> {
+    /// The same component, borrowing its names rather than copying them.
+    pub fn borrowed(&self) -> PathComponent<'_> {
+        match self {
+            PathComponent::Name(name) => PathComponent::Name(Cow::Borrowed(name)),
+            PathComponent::Index(index) => PathComponent::Index(*index),
+            PathComponent::Slice(start, end) => PathComponent::Slice(*start, *end),
+            PathComponent::Filter(predicate) => PathComponent::Filter(predicate.borrowed()),
+            PathComponent::Any => PathComponent::Any,
+            PathComponent::AnyRecursive => PathComponent::AnyRecursive,
+        }
+    }
+
+    pub fn into_owned(self) -> PathComponent<'static> {
+        match self {
+            PathComponent::Name(name) => PathComponent::Name(Cow::Owned(name.into_owned())),
+            PathComponent::Index(index) => PathComponent::Index(index),
+            PathComponent::Slice(start, end) => PathComponent::Slice(start, end),
+            PathComponent::Filter(predicate) => PathComponent::Filter(predicate.into_owned()),
+            PathComponent::Any => PathComponent::Any,
+            PathComponent::AnyRecursive => PathComponent::AnyRecursive,
+        }
+    }
+
+    fn matches_many(&self) -> bool {
+        matches!(
+            self,
+            PathComponent::Slice(..) | PathComponent::Any | PathComponent::AnyRecursive
+        )
+    }
+}
+
+/// Formats the component in its canonical syntax, quoting names where needed.
+impl fmt::Display for PathComponent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathComponent::Name(name) => {
+                let is_plain = !name.is_empty()
+                    && !name.contains(char::is_whitespace)
+                    && !matches!(name.as_ref(), "*" | "**")
+                    && parse_index(name).is_none()
+                    && !name.contains(['.', '[', ']', '"', '\\']);
+                match is_plain {
+                    true => f.write_str(name),
+                    false => write_quoted(f, name),
+                }
+            }
+            PathComponent::Index(index) => write!(f, "{}", index),
+            PathComponent::Slice(start, end) => {
+                f.write_str("[")?;
+                if let Some(start) = start {
+                    write!(f, "{}", start)?;
+                }
+                f.write_str("..")?;
+                if let Some(end) = end {
+                    write!(f, "{}", end)?;
+                }
+                f.write_str("]")
+            }
+            PathComponent::Filter(predicate) => write!(f, "[{}]", predicate),
+            PathComponent::Any => f.write_str("*"),
+            PathComponent::AnyRecursive => f.write_str("**"),
+        }
+    }
+}
+
+/// Predicate which compares the child of a file or value (e.g. `draft`) against a literal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Predicate<'a> {
+    /// path of the child, e.g. `author.name`
+    pub key: Vec<PathComponent<'a>>,
+    pub op: PredicateOp,
+    pub literal: Cow<'a, str>,
+}
+
+impl<'a> Predicate<'a> {
+    pub fn borrowed(&self) -> Predicate<'_> {
+        Predicate {
+            key: self.key.iter().map(PathComponent::borrowed).collect(),
+            op: self.op,
+            literal: Cow::Borrowed(&self.literal),
+        }
+    }
+
+    pub fn into_owned(self) -> Predicate<'static> {
+        Predicate {
+            key: self
+                .key
+                .into_iter()
+                .map(PathComponent::into_owned)
+                .collect(),
+            op: self.op,
+            literal: Cow::Owned(self.literal.into_owned()),
+        }
+    }
+}
+
+impl fmt::Display for Predicate<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_components(f, &self.key)?;
+        f.write_str(match self.op {
+            PredicateOp::Eq => "=",
+            PredicateOp::NotEq => "!=",
+            PredicateOp::Contains => "~=",
+        })?;
+        let literal = self.literal.as_ref();
+        match literal.trim() == literal && !literal.contains([']', '"', '\\']) {
+            true => f.write_str(literal),
+            false => write_quoted(f, literal),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PredicateOp {
+    /// `[key=literal]`: the child equals the literal
+    Eq,
+    /// `[key!=literal]`: the child is missing or does not equal the literal
+    NotEq,
+    /// `[key~=literal]`: the child is a list with an element equal to the literal,
+    /// or a string containing the literal
+    Contains,
+}
+
+/// A parsed path, which can be stored and resolved any number of times without parsing it again.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Path {
+    components: Vec<PathComponent<'static>>,
+}
+
+impl Path {
+    pub fn parse(s: &str) -> Result<Path, PathError> {
+        let components = PathIter::parse(s)?.map(PathComponent::into_owned).collect();
+        Ok(Path { components })
+    }
+
+    pub fn components(&self) -> &[PathComponent<'static>] {
+        &self.components
+    }
+
+    pub fn iter(&self) -> PathIter<'_> {
+        PathIter::wrap(self.components.iter().map(PathComponent::borrowed))
+    }
+
+    /// Whether the path can match more than one file or value, as it contains a wildcard or slice.
+    pub fn matches_many(&self) -> bool {
+        self.components.iter().any(PathComponent::matches_many)
+    }
+}
+
+impl<'a> AsRef<[PathComponent<'a>]> for Path {
+    fn as_ref(&self) -> &[PathComponent<'a>] {
+        &self.components
+    }
+}
+
+impl FromStr for Path {
+    type Err = PathError;
+
+    fn from_str(s: &str) -> Result<Path, PathError> {
+        Path::parse(s)
+    }
+}
+
+/// Formats the path in its canonical syntax, which parses back into the same path.
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_components(f, &self.components)
+    }
+}
+
+fn write_components(f: &mut fmt::Formatter<'_>, components: &[PathComponent]) -> fmt::Result {
+    for (i, component) in components.iter().enumerate() {
+        // brackets are attached to the component before them
+        if i > 0
+            && !matches!(
+                component,
+                PathComponent::Slice(..) | PathComponent::Filter(_)
+            )
+        {
+            f.write_str(".")?;
+        }
+        write!(f, "{}", component)?;
+    }
+    Ok(())
+}
+
+fn write_quoted(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        if matches!(c, '"' | '\\') {
+            f.write_str("\\")?;
+        }
+        write!(f, "{}", c)?;
+    }
+    f.write_str("\"")
+}
+
+/// Position of an index within a collection of the given length, if within bounds.
+pub fn resolve_index(index: isize, len: usize) -> Option<usize> {
+    let position = if index < 0 {
+        len.checked_sub(index.unsigned_abs())?
+    } else {
+        index as usize
+    };
+    if position < len {
+        Some(position)
+    } else {
+        None
+    }
+}
+
+/// Positions of a slice within a collection of the given length, clamped to its bounds.
+pub fn resolve_range(start: Option<isize>, end: Option<isize>, len: usize) -> Range<usize> {
+    let bound = |index: isize| {
+        if index < 0 {
+            len.saturating_sub(index.unsigned_abs())
+        } else {
+            (index as usize).min(len)
+        }
+    };
+    let start = start.map_or(0, bound);
+    let end = end.map_or(len, bound);
+    start..end.max(start)
+}
+
 pub struct PathIter<'a> {
     it: Box<dyn Iterator<Item = PathComponent<'a>> + 'a>,
     last: Option<PathComponent<'a>>,
 }
 
 impl<'a> PathIter<'a> {
-    pub fn new<S: AsRef<str> + ?Sized>(s: &'a S) -> PathIter<'a> {
-        let it = s.as_ref().split(".").filter(|s| s.trim() != "").map(|s| match s.trim() {
-            "*" => PathComponent::Any,
-            "**" => PathComponent::AnyRecursive,
-            name => PathComponent::Name(name),
-        });
-        PathIter {
-            it: Box::new(it),
-            last: None,
-        }
+    pub fn parse<S: AsRef<str> + ?Sized>(s: &'a S) -> Result<PathIter<'a>, PathError> {
+        let components = Parser::new(s.as_ref()).parse_path()?;
+        Ok(PathIter::wrap(components.into_iter()))
     }
 
     pub fn wrap<I: Iterator<Item = PathComponent<'a>> + 'a>(it: I) -> PathIter<'a> {
@@ -36,51 +268,310 @@ impl<'a> Iterator for PathIter<'a> {
 
     fn next(&mut self) -> Option<PathComponent<'a>> {
         loop {
-            match self.it.next() {
+            let c = match self.it.next() {
                 None => {
                     self.last = None;
                     return None;
                 }
-                Some(c) => match c {
-                    PathComponent::Any => match self.last {
-                        None => {
-                            self.last = Some(PathComponent::Any);
-                            return self.last;
-                        }
-                        Some(pc) => match pc {
-                            PathComponent::Name(_) => {
-                                self.last = Some(PathComponent::Any);
-                                return self.last;
-                            }
-                            PathComponent::Any | PathComponent::AnyRecursive => continue,
-                        },
-                    },
-                    PathComponent::AnyRecursive => match self.last {
-                        None => {
-                            self.last = Some(PathComponent::AnyRecursive);
-                            return self.last;
-                        }
-                        Some(pc) => match pc {
-                            PathComponent::Name(_) | PathComponent::Any => {
-                                self.last = Some(PathComponent::AnyRecursive);
-                                return self.last;
-                            }
-                            PathComponent::AnyRecursive => continue,
-                        },
-                    },
-                    PathComponent::Name(_) => {
-                        self.last = Some(c);
-                        return self.last;
+                Some(c) => c,
+            };
+            // consecutive wildcards are merged, with `**` taking precedence over `*`
+            match (&c, &self.last) {
+                (PathComponent::Any, Some(PathComponent::Any | PathComponent::AnyRecursive))
+                | (PathComponent::AnyRecursive, Some(PathComponent::AnyRecursive)) => continue,
+                _ => {
+                    self.last = Some(c.clone());
+                    return Some(c);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathError {
+    UnterminatedQuote(usize),
+    UnterminatedBracket(usize),
+    UnterminatedEscape,
+    UnexpectedCharacter(char, usize),
+    EmptyComponent(usize),
+    InvalidBrackets(String),
+}
+
+impl Error for PathError {}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathError::UnterminatedQuote(pos) => write!(f, "unterminated quote at {}", pos),
+            PathError::UnterminatedBracket(pos) => write!(f, "unterminated bracket at {}", pos),
+            PathError::UnterminatedEscape => write!(f, "unterminated escape at the end"),
+            PathError::UnexpectedCharacter(c, pos) => {
+                write!(f, "unexpected character '{}' at {}", c, pos)
+            }
+            PathError::EmptyComponent(pos) => write!(f, "empty component at {}", pos),
+            PathError::InvalidBrackets(s) => write!(f, "invalid brackets: [{}]", s),
+        }
+    }
+}
+
+// a path consists of components separated by dots, where each component is a name,
+// optionally quoted, followed by any amount of bracketed slices, indices or predicates
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(s: &'a str) -> Parser<'a> {
+        Parser { s, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.s[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    // leading and trailing dots are ignored, such that e.g. `.foo.` is the same path as `foo`
+    fn at_end(&self) -> bool {
+        self.s[self.pos..]
+            .trim_matches(|c: char| c == '.' || c.is_whitespace())
+            .is_empty()
+    }
+
+    fn parse_path(mut self) -> Result<Vec<PathComponent<'a>>, PathError> {
+        let mut components = Vec::new();
+        while self.peek().is_some_and(|c| c == '.' || c.is_whitespace()) {
+            self.bump();
+        }
+        while !self.at_end() {
+            let start = self.pos;
+            if !self.parse_component(&mut components)? {
+                return Err(PathError::EmptyComponent(start));
+            }
+            match self.bump() {
+                None | Some('.') => (),
+                Some(c) => return Err(PathError::UnexpectedCharacter(c, self.pos - c.len_utf8())),
+            }
+        }
+        Ok(components)
+    }
+
+    // parse a single component, returning false in case it is empty
+    fn parse_component(
+        &mut self,
+        components: &mut Vec<PathComponent<'a>>,
+    ) -> Result<bool, PathError> {
+        self.skip_whitespace();
+        let mut parsed = true;
+        if self.peek() == Some('"') {
+            // a quoted name is always a name, e.g. `"*"` or `"0"`
+            components.push(PathComponent::Name(self.parse_quoted()?));
+        } else {
+            let start = self.pos;
+            let mut end = start;
+            let mut escaped = false;
+            while let Some(c) = self.peek() {
+                match c {
+                    '.' | '[' => break,
+                    '"' | ']' => return Err(PathError::UnexpectedCharacter(c, self.pos)),
+                    '\\' => {
+                        self.bump();
+                        self.bump().ok_or(PathError::UnterminatedEscape)?;
+                        escaped = true;
+                    }
+                    _ => {
+                        self.bump();
                     }
                 }
+                if !c.is_whitespace() {
+                    end = self.pos;
+                }
+            }
+            let name = &self.s[start..end];
+            // an escaped name is always a name as well, e.g. `\*`
+            let component = match escaped {
+                true => Some(PathComponent::Name(unescape(name))),
+                false => parse_name(name),
+            };
+            parsed = component.is_some();
+            components.extend(component);
+        }
+        self.skip_whitespace();
+        while self.peek() == Some('[') {
+            let inner = self.parse_brackets()?;
+            let component =
+                parse_bracket(inner).ok_or_else(|| PathError::InvalidBrackets(inner.to_owned()))?;
+            components.push(component);
+            parsed = true;
+            self.skip_whitespace();
+        }
+        Ok(parsed)
+    }
+
+    fn parse_quoted(&mut self) -> Result<Cow<'a, str>, PathError> {
+        let start = self.pos;
+        self.bump();
+        let mut escaped = false;
+        loop {
+            match self.bump() {
+                None => return Err(PathError::UnterminatedQuote(start)),
+                Some('"') => break,
+                Some('\\') => {
+                    self.bump().ok_or(PathError::UnterminatedEscape)?;
+                    escaped = true;
+                }
+                Some(_) => (),
             }
         }
+        let name = &self.s[start + 1..self.pos - 1];
+        Ok(match escaped {
+            true => unescape(name),
+            false => Cow::Borrowed(name),
+        })
+    }
+
+    // the content between brackets, which can contain quoted brackets of its own
+    fn parse_brackets(&mut self) -> Result<&'a str, PathError> {
+        let start = self.pos;
+        self.bump();
+        let mut quoted = false;
+        loop {
+            match self.bump() {
+                None => return Err(PathError::UnterminatedBracket(start)),
+                Some(']') if !quoted => break,
+                Some('"') => quoted = !quoted,
+                Some('\\') => {
+                    self.bump().ok_or(PathError::UnterminatedEscape)?;
+                }
+                Some(_) => (),
+            }
+        }
+        Ok(&self.s[start + 1..self.pos - 1])
     }
 }
 
-impl<'a> From<&'a str> for PathIter<'a> {
-    fn from(s: &'a str) -> PathIter<'a> {
-        PathIter::new(s)
+// replace every backslash escape by the character it escapes
+fn unescape(s: &str) -> Cow<'_, str> {
+    if !s.contains('\\') {
+        return Cow::Borrowed(s);
+    }
+    let mut output = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => output.extend(chars.next()),
+            c => output.push(c),
+        }
+    }
+    Cow::Owned(output)
+}
+
+fn parse_name(name: &str) -> Option<PathComponent<'_>> {
+    Some(match name {
+        "" => return None,
+        "*" => PathComponent::Any,
+        "**" => PathComponent::AnyRecursive,
+        _ => match parse_index(name) {
+            Some(index) => PathComponent::Index(index),
+            None => PathComponent::Name(Cow::Borrowed(name)),
+        },
+    })
+}
+
+// only the canonical form of an integer is an index, such that e.g. `007` remains a name
+fn parse_index(s: &str) -> Option<isize> {
+    let index: isize = s.parse().ok()?;
+    if index.to_string() == s {
+        Some(index)
+    } else {
+        None
+    }
+}
+
+// parse the content of brackets such as `[2..5]`, `[0]` or `[draft=false]`
+fn parse_bracket(s: &str) -> Option<PathComponent<'_>> {
+    let s = s.trim();
+    Some(match parse_predicate(s) {
+        Some(predicate) => PathComponent::Filter(predicate),
+        None => match s.split_once("..") {
+            Some((start, end)) => PathComponent::Slice(parse_bound(start)?, parse_bound(end)?),
+            None => PathComponent::Index(parse_index(s)?),
+        },
+    })
+}
+
+fn parse_predicate(s: &str) -> Option<Predicate<'_>> {
+    let (key, literal) = s.split_at(find_unquoted(s, '=')?);
+    let literal = &literal[1..];
+    let (key, op) = if let Some(key) = key.strip_suffix('!') {
+        (key, PredicateOp::NotEq)
+    } else if let Some(key) = key.strip_suffix('~') {
+        (key, PredicateOp::Contains)
+    } else {
+        (key, PredicateOp::Eq)
+    };
+    // the key is a path of its own, e.g. `author.name`
+    let key: Vec<PathComponent> =
+        PathIter::wrap(Parser::new(key).parse_path().ok()?.into_iter()).collect();
+    if key.is_empty() {
+        return None;
+    }
+    let literal = literal.trim();
+    // a literal can be quoted, e.g. to keep its leading or trailing whitespace
+    let literal = match literal.strip_prefix('"') {
+        Some(quoted) => unescape(quoted.strip_suffix('"')?),
+        None => unescape(literal),
+    };
+    Some(Predicate { key, op, literal })
+}
+
+// position of the first occurrence of the character which is not quoted or escaped
+fn find_unquoted(s: &str, needle: char) -> Option<usize> {
+    let mut quoted = false;
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == needle && !quoted => return Some(i),
+            _ => (),
+        }
+    }
+    None
+}
+
+fn parse_bound(s: &str) -> Option<Option<isize>> {
+    match s.trim() {
+        "" => Some(None),
+        s => parse_index(s).map(Some),
+    }
+}
+
+impl<'a> From<&'a Path> for PathIter<'a> {
+    fn from(path: &'a Path) -> PathIter<'a> {
+        path.iter()
+    }
+}
+
+impl<'a> TryFrom<&'a str> for PathIter<'a> {
+    type Error = PathError;
+
+    fn try_from(s: &'a str) -> Result<PathIter<'a>, PathError> {
+        PathIter::parse(s)
     }
 }
 
@@ -94,34 +585,34 @@ mod tests {
             ("", vec![]),
             (".", vec![]),
             ("...", vec![]),
-            ("foo.", vec![PathComponent::Name("foo")]),
-            (".foo.", vec![PathComponent::Name("foo")]),
+            ("foo.", vec![PathComponent::Name("foo".into())]),
+            (".foo.", vec![PathComponent::Name("foo".into())]),
             (
                 "foo.bar.baz",
                 vec![
-                    PathComponent::Name("foo"),
-                    PathComponent::Name("bar"),
-                    PathComponent::Name("baz"),
+                    PathComponent::Name("foo".into()),
+                    PathComponent::Name("bar".into()),
+                    PathComponent::Name("baz".into()),
                 ],
             ),
             (
                 "foo.*.bar.**.baz",
                 vec![
-                    PathComponent::Name("foo"),
+                    PathComponent::Name("foo".into()),
                     PathComponent::Any,
-                    PathComponent::Name("bar"),
+                    PathComponent::Name("bar".into()),
                     PathComponent::AnyRecursive,
-                    PathComponent::Name("baz"),
+                    PathComponent::Name("baz".into()),
                 ],
             ),
             (
                 "foo.*.bar.**.baz.*.*.**.*",
                 vec![
-                    PathComponent::Name("foo"),
+                    PathComponent::Name("foo".into()),
                     PathComponent::Any,
-                    PathComponent::Name("bar"),
+                    PathComponent::Name("bar".into()),
                     PathComponent::AnyRecursive,
-                    PathComponent::Name("baz"),
+                    PathComponent::Name("baz".into()),
                     PathComponent::Any,
                     PathComponent::AnyRecursive,
                 ],
@@ -129,18 +620,122 @@ mod tests {
             (
                 "  foo.   bar .baz ",
                 vec![
-                    PathComponent::Name("foo"),
-                    PathComponent::Name("bar"),
-                    PathComponent::Name("baz"),
+                    PathComponent::Name("foo".into()),
+                    PathComponent::Name("bar".into()),
+                    PathComponent::Name("baz".into()),
                 ],
             ),
             (
                 " 1*2.***",
-                vec![PathComponent::Name("1*2"), PathComponent::Name("***")],
+                vec![
+                    PathComponent::Name("1*2".into()),
+                    PathComponent::Name("***".into()),
+                ],
+            ),
+            (
+                "posts.0.title",
+                vec![
+                    PathComponent::Name("posts".into()),
+                    PathComponent::Index(0),
+                    PathComponent::Name("title".into()),
+                ],
+            ),
+            (
+                "posts.-1.007",
+                vec![
+                    PathComponent::Name("posts".into()),
+                    PathComponent::Index(-1),
+                    PathComponent::Name("007".into()),
+                ],
+            ),
+            (
+                "posts[2..5].title",
+                vec![
+                    PathComponent::Name("posts".into()),
+                    PathComponent::Slice(Some(2), Some(5)),
+                    PathComponent::Name("title".into()),
+                ],
+            ),
+            (
+                "posts[..3][-1]",
+                vec![
+                    PathComponent::Name("posts".into()),
+                    PathComponent::Slice(None, Some(3)),
+                    PathComponent::Index(-1),
+                ],
+            ),
+            (
+                "posts.*[draft=false].title",
+                vec![
+                    PathComponent::Name("posts".into()),
+                    PathComponent::Any,
+                    PathComponent::Filter(Predicate {
+                        key: vec![PathComponent::Name("draft".into())],
+                        op: PredicateOp::Eq,
+                        literal: "false".into(),
+                    }),
+                    PathComponent::Name("title".into()),
+                ],
+            ),
+            (
+                "**[ type != \"page\" ][author.name~=Glen]",
+                vec![
+                    PathComponent::AnyRecursive,
+                    PathComponent::Filter(Predicate {
+                        key: vec![PathComponent::Name("type".into())],
+                        op: PredicateOp::NotEq,
+                        literal: "page".into(),
+                    }),
+                    PathComponent::Filter(Predicate {
+                        key: vec![
+                            PathComponent::Name("author".into()),
+                            PathComponent::Name("name".into()),
+                        ],
+                        op: PredicateOp::Contains,
+                        literal: "Glen".into(),
+                    }),
+                ],
+            ),
+            (
+                "posts.[-2..]",
+                vec![
+                    PathComponent::Name("posts".into()),
+                    PathComponent::Slice(Some(-2), None),
+                ],
+            ),
+            (
+                "foo.\"v1.2\".\" first name \"",
+                vec![
+                    PathComponent::Name("foo".into()),
+                    PathComponent::Name("v1.2".into()),
+                    PathComponent::Name(" first name ".into()),
+                ],
+            ),
+            (
+                "releases.release-1\\.0.\"*\".\\0.\"say \\\"hi\\\"\"",
+                vec![
+                    PathComponent::Name("releases".into()),
+                    PathComponent::Name("release-1.0".into()),
+                    PathComponent::Name("*".into()),
+                    PathComponent::Name("0".into()),
+                    PathComponent::Name("say \"hi\"".into()),
+                ],
+            ),
+            (
+                "posts.*[title=\"a.b]c\"]",
+                vec![
+                    PathComponent::Name("posts".into()),
+                    PathComponent::Any,
+                    PathComponent::Filter(Predicate {
+                        key: vec![PathComponent::Name("title".into())],
+                        op: PredicateOp::Eq,
+                        literal: "a.b]c".into(),
+                    }),
+                ],
             ),
         ];
         for (input_str, expected_output_vec) in test_cases {
-            let path_iter: PathIter = input_str.into();
+            let path_iter = PathIter::parse(input_str).unwrap();
             let output_vec: Vec<PathComponent> = path_iter.collect();
             let matching = output_vec
                 .iter()
@@ -151,4 +746,79 @@ mod tests {
             assert_eq!(matching, output_vec.len());
         }
     }
+
+    #[test]
+    fn test_parse_error() {
+        let test_cases = vec![
+            ("foo..bar", PathError::EmptyComponent(4)),
+            ("foo.\"bar", PathError::UnterminatedQuote(4)),
+            ("foo.bar\\", PathError::UnterminatedEscape),
+            ("foo[0", PathError::UnterminatedBracket(3)),
+            ("foo[bar]", PathError::InvalidBrackets(String::from("bar"))),
+            ("foo\"bar\"", PathError::UnexpectedCharacter('"', 3)),
+            ("\"foo\"bar", PathError::UnexpectedCharacter('b', 5)),
+            ("foo]", PathError::UnexpectedCharacter(']', 3)),
+        ];
+        for (input_str, expected_err) in test_cases {
+            assert_eq!(
+                PathIter::parse(input_str).err(),
+                Some(expected_err.clone()),
+                "{}",
+                input_str
+            );
+            assert_eq!(
+                PathIter::try_from(input_str).err(),
+                Some(expected_err),
+                "{}",
+                input_str
+            );
+        }
+        let path_iter = PathIter::try_from("foo.bar").unwrap();
+        assert_eq!(path_iter.count(), 2);
+    }
+
+    #[test]
+    fn test_path() {
+        let test_cases = vec![
+            ("", ""),
+            (" .foo. bar.", "foo.bar"),
+            ("posts.*[draft = false].title", "posts.*[draft=false].title"),
+            ("posts.-1.\"007\".\\0", "posts.-1.007.\"0\""),
+            ("**.*.**[..3][-2..-1]", "**[..3][-2..-1]"),
+            (
+                "[author.\"first name\"~=\" Glen\"]",
+                "[author.\"first name\"~=\" Glen\"]",
+            ),
+            ("foo.\"v1.2\".\"*\"", "foo.\"v1.2\".\"*\""),
+            ("\"say \\\"hi\\\"\"", "\"say \\\"hi\\\"\""),
+        ];
+        for (input_str, expected_str) in test_cases {
+            let path: Path = input_str.parse().unwrap();
+            assert_eq!(path.to_string(), expected_str);
+            // the canonical syntax parses back into the same path
+            assert_eq!(Path::parse(&path.to_string()).unwrap(), path);
+            let expected_output_vec: Vec<PathComponent> =
+                PathIter::parse(input_str).unwrap().collect();
+            let output_vec: Vec<PathComponent> = PathIter::from(&path).collect();
+            assert_eq!(output_vec, expected_output_vec);
+        }
+        assert!(Path::parse("posts[..3]").unwrap().matches_many());
+        assert!(!Path::parse("posts.0").unwrap().matches_many());
+        assert_eq!(
+            Path::parse("foo..bar").err(),
+            Some(PathError::EmptyComponent(4))
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve_index(0, 3), Some(0));
+        assert_eq!(resolve_index(-1, 3), Some(2));
+        assert_eq!(resolve_index(3, 3), None);
+        assert_eq!(resolve_index(-4, 3), None);
+        assert_eq!(resolve_range(Some(1), None, 3), 1..3);
+        assert_eq!(resolve_range(None, Some(10), 3), 0..3);
+        assert_eq!(resolve_range(Some(-2), Some(-1), 3), 1..2);
+        assert_eq!(resolve_range(Some(2), Some(1), 3), 2..2);
+    }
 }
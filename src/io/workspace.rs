@@ -1,45 +1,82 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, VecDeque};
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use super::path::{PathComponent, PathIter};
-use super::{File, FileFormat, FileInfo};
+use super::data::matches_predicate;
+use super::path::{resolve_index, resolve_range, Path as ParsedPath, PathComponent, Predicate};
+use super::{File, FileFormat, FileInfo, FileLocale, Meta};
 use super::{Value, ValueIter};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+
+/// Names of the site configuration file, found at the root of a workspace.
+const CONFIG_FILES: [(&str, FileFormat); 4] = [
+    ("tsg.yml", FileFormat::Yaml),
+    ("tsg.yaml", FileFormat::Yaml),
+    ("tsg.json", FileFormat::Json),
+    ("tsg.toml", FileFormat::Toml),
+];
 
 pub struct Workspace {
     root: PathBuf,
+    config: Meta,
     assets: Vec<PathBuf>,
     pages: FileEntry,
     layouts: FileEntry,
     includes: FileEntry,
+    data: FileEntry,
+    locales: Vec<FileLocale>,
+    locale: Option<FileLocale>,
 }
 
 impl Workspace {
     pub fn read<P: AsRef<Path>>(path: P) -> Result<Workspace> {
         let path = path.as_ref();
 
+        let config = read_config(path)?;
+
         let assets = list_files(path.join("assets"))?;
 
-        let pages = load_files(path.join("pages"), &|file_info| match file_info.format() {
-            FileFormat::Html | FileFormat::Markdown | FileFormat::Rhai => true,
-            _ => false,
+        let pages = load_files(path, "pages", &|file_info| {
+            matches!(
+                file_info.format(),
+                FileFormat::Html | FileFormat::Markdown | FileFormat::Rhai
+            )
         })?;
 
-        let layouts = load_files(path.join("layouts"), &|file_info| match file_info.format() {
-            FileFormat::Html => true,
-            _ => false,
+        let layouts = load_files(path, "layouts", &|file_info| {
+            matches!(file_info.format(), FileFormat::Html)
+        })?;
+
+        let includes = load_files(path, "includes", &|_| true)?;
+
+        let data = load_files(path, "data", &|file_info| {
+            matches!(
+                file_info.format(),
+                FileFormat::Yaml
+                    | FileFormat::Json
+                    | FileFormat::Toml
+                    | FileFormat::Csv
+                    | FileFormat::Tsv
+            )
         })?;
 
-        let includes = load_files(path.join("includes"), &|_| true)?;
+        let mut locales = Vec::new();
+        for entry in [&pages, &layouts, &includes, &data] {
+            collect_locales(entry, &mut locales);
+        }
+        locales.sort();
 
         Ok(Workspace {
             root: PathBuf::from(path),
+            config,
             assets,
             pages,
             layouts,
             includes,
+            data,
+            locales,
+            locale: None,
         })
     }
 
@@ -47,56 +84,290 @@ impl Workspace {
         self.root.as_path()
     }
 
+    /// Site configuration, read from the `tsg.yml`, `tsg.yaml`, `tsg.json` or `tsg.toml` file
+    /// at the root of the workspace, and empty if there is no such file.
+    pub fn config(&self) -> &Meta {
+        &self.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut Meta {
+        &mut self.config
+    }
+
+    /// Locale of the root of the website, as configured by `default_locale` in the site configuration.
+    /// Without it the root of the website is unlocalized.
+    pub fn default_locale(&self) -> Result<Option<FileLocale>> {
+        match self.config.value(&ParsedPath::parse("default_locale")?) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(locale)) => Ok(Some(
+                locale
+                    .parse()
+                    .with_context(|| format!("invalid default_locale {}", locale))?,
+            )),
+            Some(_) => Err(anyhow!("default_locale is expected to be a string")),
+        }
+    }
+
+    /// All locales the website is built for, starting with the locale of its root,
+    /// which is `None` when the root is unlocalized, followed by all other locales found.
+    pub fn site_locales(&self) -> Result<Vec<Option<FileLocale>>> {
+        let default_locale = self.default_locale()?;
+        let mut locales = vec![default_locale.clone()];
+        locales.extend(
+            self.locales
+                .iter()
+                .filter(|locale| Some(*locale) != default_locale.as_ref())
+                .cloned()
+                .map(Some),
+        );
+        Ok(locales)
+    }
+
     pub fn assets(&self) -> &[PathBuf] {
         &self.assets[..]
     }
 
-    pub fn page_or_value<'a, 'b, T>(&'a mut self, t: T) -> Option<FileOrValue<'a>>
+    /// All assets grouped by name, with the locale variants of each asset found using
+    /// the same suffix rules as other files, e.g. `main.css` and `main.accessible.css`.
+    /// Only a suffix which is one of the site locales is seen as a locale,
+    /// such that the `min` of `jquery.min.js` remains part of its name.
+    pub fn localized_assets(&self) -> Result<Vec<LocalizedAsset>> {
+        let locales: Vec<FileLocale> = self.site_locales()?.into_iter().flatten().collect();
+        let mut assets: Vec<LocalizedAsset> = Vec::new();
+        for path in &self.assets {
+            let (unlocalized_path, locale) = split_asset_locale(path, &locales);
+            match assets.iter_mut().find(|asset| asset.path == unlocalized_path) {
+                Some(asset) => asset.variants.push((locale, path.clone())),
+                None => assets.push(LocalizedAsset {
+                    path: unlocalized_path,
+                    variants: vec![(locale, path.clone())],
+                }),
+            }
+        }
+        Ok(assets)
+    }
+
+    /// All locales for which a localized page, layout, include or data file exists, in sorted order.
+    pub fn locales(&self) -> &[FileLocale] {
+        &self.locales[..]
+    }
+
+    /// Locale used to resolve localized files, if any.
+    pub fn locale(&self) -> Option<&FileLocale> {
+        self.locale.as_ref()
+    }
+
+    /// Resolve files for the given locale from now on. A file is resolved by trying
+    /// the locale and the locales it falls back to (e.g. `en-GB` and `en` for `en-GB`),
+    /// ending with the unlocalized file. Without a locale only unlocalized files are resolved.
+    pub fn set_locale(&mut self, locale: Option<FileLocale>) {
+        self.locale = locale;
+    }
+
+    /// All site locales (see [`Workspace::site_locales`]) the given page resolves for.
+    pub fn page_locales(&self, info: &FileInfo) -> Result<Vec<Option<FileLocale>>> {
+        let mut entry = &self.pages;
+        let dirs = info.directory().unwrap_or_default().split(['/', '\\']);
+        for name in dirs.filter(|s| !s.is_empty()).chain(std::iter::once(info.name())) {
+            entry = match entry.dir.as_ref().and_then(|map| map.get(&name.to_lowercase())) {
+                Some(entry) => entry,
+                None => return Ok(Vec::new()),
+            };
+        }
+        let file = match &entry.file {
+            Some(file) => file,
+            None => return Ok(Vec::new()),
+        };
+        Ok(self
+            .site_locales()?
+            .into_iter()
+            .filter(|locale| {
+                let locales = locale.as_ref().map(FileLocale::fallbacks).unwrap_or_default();
+                !file.variant_indices(&locales).is_empty()
+            })
+            .collect())
+    }
+
+    fn fallback_locales(&self) -> Vec<FileLocale> {
+        self.locale
+            .as_ref()
+            .map(FileLocale::fallbacks)
+            .unwrap_or_default()
+    }
+
+    pub fn page_or_value<'a, 'b, P>(&'a mut self, path: &'b P) -> Option<FileOrValue<'a>>
+    where
+        P: AsRef<[PathComponent<'b>]> + ?Sized,
+    {
+        self.page_or_value_iter(path).next()
+    }
+
+    pub fn page_or_value_iter<'a, 'b, P>(&'a mut self, path: &'b P) -> FileOrValueIter<'a, 'b>
     where
-        T: Into<PathIter<'b>>,
+        P: AsRef<[PathComponent<'b>]> + ?Sized,
     {
-        self.page_or_value_iter(t).next()
+        let locales = self.fallback_locales();
+        FileOrValueIter::new(&mut self.pages, path.as_ref(), locales)
     }
 
-    pub fn page_or_value_iter<'a, 'b, T>(&'a mut self, t: T) -> FileOrValueIter<'a, 'b>
+    pub fn layout_or_value<'a, 'b, P>(&'a mut self, path: &'b P) -> Option<FileOrValue<'a>>
     where
-        T: Into<PathIter<'b>>,
+        P: AsRef<[PathComponent<'b>]> + ?Sized,
     {
-        FileOrValueIter::new(&mut self.pages, t)
+        self.layout_or_value_iter(path).next()
     }
 
-    pub fn layout_or_value<'a, 'b, T>(&'a mut self, t: T) -> Option<FileOrValue<'a>>
+    pub fn layout_or_value_iter<'a, 'b, P>(&'a mut self, path: &'b P) -> FileOrValueIter<'a, 'b>
     where
-        T: Into<PathIter<'b>>,
+        P: AsRef<[PathComponent<'b>]> + ?Sized,
     {
-        self.layout_or_value_iter(t).next()
+        let locales = self.fallback_locales();
+        FileOrValueIter::new(&mut self.layouts, path.as_ref(), locales)
     }
 
-    pub fn layout_or_value_iter<'a, 'b, T>(&'a mut self, t: T) -> FileOrValueIter<'a, 'b>
+    pub fn include_or_value<'a, 'b, P>(&'a mut self, path: &'b P) -> Option<FileOrValue<'a>>
     where
-        T: Into<PathIter<'b>>,
+        P: AsRef<[PathComponent<'b>]> + ?Sized,
     {
-        FileOrValueIter::new(&mut self.layouts, t)
+        self.include_or_value_iter(path).next()
     }
 
-    pub fn include_or_value<'a, 'b, T>(&'a mut self, t: T) -> Option<FileOrValue<'a>>
+    pub fn include_or_value_iter<'a, 'b, P>(&'a mut self, path: &'b P) -> FileOrValueIter<'a, 'b>
     where
-        T: Into<PathIter<'b>>,
+        P: AsRef<[PathComponent<'b>]> + ?Sized,
     {
-        self.include_or_value_iter(t).next()
+        let locales = self.fallback_locales();
+        FileOrValueIter::new(&mut self.includes, path.as_ref(), locales)
     }
 
-    pub fn include_or_value_iter<'a, 'b, T>(&'a mut self, t: T) -> FileOrValueIter<'a, 'b>
+    pub fn data<'a, 'b, P>(&'a mut self, path: &'b P) -> Option<&'a Value>
     where
-        T: Into<PathIter<'b>>,
+        P: AsRef<[PathComponent<'b>]> + ?Sized,
     {
-        FileOrValueIter::new(&mut self.includes, t)
+        self.data_iter(path).next()
+    }
+
+    /// Iterate over the values found within the data directory,
+    /// with a data file found as a whole represented by its entire content.
+    pub fn data_iter<'a, 'b, P>(&'a mut self, path: &'b P) -> DataIter<'a, 'b>
+    where
+        P: AsRef<[PathComponent<'b>]> + ?Sized,
+    {
+        let locales = self.fallback_locales();
+        DataIter {
+            it: FileOrValueIter::new(&mut self.data, path.as_ref(), locales),
+        }
     }
 }
 
-enum FileEntry {
-    File(LazyFile),
-    Dir(HashMap<String, FileEntry>),
+pub struct DataIter<'a, 'b> {
+    it: FileOrValueIter<'a, 'b>,
+}
+
+impl<'a, 'b> Iterator for DataIter<'a, 'b> {
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<&'a Value> {
+        loop {
+            match self.it.next()? {
+                FileOrValue::Value(value) => return Some(value),
+                FileOrValue::File(file) => match file.meta() {
+                    Some(meta) => return Some(meta.as_value()),
+                    // a data file without any content has no value
+                    None => continue,
+                },
+            }
+        }
+    }
+}
+
+/// An asset together with all its locale variants.
+pub struct LocalizedAsset {
+    path: PathBuf,
+    variants: Vec<(Option<FileLocale>, PathBuf)>,
+}
+
+impl LocalizedAsset {
+    /// Path of the asset without its locale, relative to the assets directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Paths of all variants of the asset, relative to the assets directory.
+    pub fn variant_paths(&self) -> impl Iterator<Item = &Path> {
+        self.variants.iter().map(|(_, path)| path.as_path())
+    }
+
+    /// Path of the variant to use for the given locale, relative to the assets directory,
+    /// trying the locales it falls back to before falling back to the unlocalized asset.
+    pub fn resolve(&self, locale: Option<&FileLocale>) -> Option<&Path> {
+        let locales = locale.map(FileLocale::fallbacks).unwrap_or_default();
+        locales
+            .iter()
+            .map(Some)
+            .chain(std::iter::once(None))
+            .find_map(|locale| {
+                self.variants
+                    .iter()
+                    .find(|(variant_locale, _)| variant_locale.as_ref() == locale)
+            })
+            .map(|(_, path)| path.as_path())
+    }
+}
+
+/// A file, a directory, or both in case a file shares its name with a directory,
+/// e.g. `blog.md` and `blog/`, where a path continues within the directory
+/// and falls back to the metadata of the file for any entry not found within it.
+#[derive(Default)]
+struct FileEntry {
+    file: Option<LocalizedFile>,
+    dir: Option<BTreeMap<String, FileEntry>>,
+}
+
+/// All locale variants of a file, e.g. `footer.html` and `footer.nl.html`.
+struct LocalizedFile {
+    variants: Vec<LazyFile>,
+}
+
+impl LocalizedFile {
+    /// Read the first variant found for the given locales,
+    /// falling back to the unlocalized variant if none is found.
+    fn read_or_get_file(&mut self, locales: &[FileLocale]) -> Option<&File> {
+        let index = *self.variant_indices(locales).first()?;
+        self.variants[index].read_or_get_file().ok()
+    }
+
+    /// Read all variants found for the given locales, followed by the unlocalized variant,
+    /// ordered from the most to the least preferred variant.
+    fn read_or_get_files<'a>(&'a mut self, locales: &[FileLocale]) -> Vec<&'a File> {
+        let indices = self.variant_indices(locales);
+        for index in indices.iter() {
+            // files which fail to be read are treated as if they do not exist
+            let _ = self.variants[*index].read_or_get_file();
+        }
+        let variants: &'a [LazyFile] = &self.variants;
+        indices
+            .into_iter()
+            .filter_map(|index| match &variants[index] {
+                LazyFile::File(file) => Some(file),
+                LazyFile::FileInfo(_) => None,
+            })
+            .collect()
+    }
+
+    /// Indices of the variants found for the given locales, followed by the unlocalized variant.
+    fn variant_indices(&self, locales: &[FileLocale]) -> Vec<usize> {
+        locales
+            .iter()
+            .map(Some)
+            .chain(std::iter::once(None))
+            .filter_map(|locale| {
+                self.variants
+                    .iter()
+                    .position(|variant| variant.info().locale() == locale)
+            })
+            .collect()
+    }
 }
 
 enum LazyFile {
@@ -105,6 +376,13 @@ enum LazyFile {
 }
 
 impl LazyFile {
+    pub fn info(&self) -> &FileInfo {
+        match self {
+            LazyFile::File(file) => file.info(),
+            LazyFile::FileInfo(info) => info,
+        }
+    }
+
     pub fn read_or_get_file(&mut self) -> Result<&File> {
         match self {
             LazyFile::File(file) => Ok(file),
@@ -125,44 +403,118 @@ pub enum FileOrValue<'a> {
 enum FileEntryOrValueInnerState<'a, 'b> {
     None,
     FileEntry(FileEntryState<'a, 'b>),
-    ValueIter(ValueIter<'a, 'b>),
+    // one iterator per locale variant of a file, in fallback order
+    ValueIters(VecDeque<ValueIter<'a, 'b>>),
 }
 
 struct FileEntryState<'a, 'b> {
-    pub path: Vec<PathComponent<'b>>,
+    pub path: &'b [PathComponent<'b>],
     pub entry_ref: &'a mut FileEntry,
     pub path_index: usize,
     pub recursive: bool,
 }
 
-fn load_files<P: AsRef<Path>>(dir: P, filter: &dyn Fn(&FileInfo) -> bool) -> Result<FileEntry> {
-    let mut files = HashMap::new();
+fn load_files(root: &Path, kind: &str, filter: &dyn Fn(&FileInfo) -> bool) -> Result<FileEntry> {
+    let dir = root.join(kind);
+    // offset of the separator in front of the kind directory
+    let offset = dir.as_os_str().len().saturating_sub(kind.len() + 1);
+    load_files_at(&dir, offset, filter)
+}
+
+fn load_files_at(dir: &Path, offset: usize, filter: &dyn Fn(&FileInfo) -> bool) -> Result<FileEntry> {
+    let mut files = BTreeMap::new();
 
-    let dir = dir.as_ref();
     if !dir.exists() {
-        return Ok(FileEntry::Dir(files));
+        return Ok(FileEntry { file: None, dir: Some(files) });
     }
 
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
         let path = entry.path();
         if path.is_dir() {
-            let dir = load_files(&path, filter)?;
+            let dir = load_files_at(&path, offset, filter)?;
             match path.file_name().and_then(|n| n.to_str()) {
-                Some(dir_name) => files.insert(dir_name.to_lowercase(), dir),
+                Some(dir_name) => files.entry(dir_name.to_lowercase()).or_default().dir = dir.dir,
                 None => return Err(anyhow!("failed to get dirname for dir entry")),
             };
         } else {
-            let file_info: FileInfo = (&path).try_into()?;
+            let file_info = match path.to_str() {
+                Some(path) => FileInfo::new_at(path, offset)?,
+                None => return Err(anyhow!("invalid file path {}", path.display())),
+            };
             if filter(&file_info) {
-                files.insert(file_info.name().to_lowercase(), FileEntry::File(LazyFile::FileInfo(file_info)));
+                files
+                    .entry(file_info.name().to_lowercase())
+                    .or_default()
+                    .file
+                    .get_or_insert_with(|| LocalizedFile { variants: Vec::new() })
+                    .variants
+                    .push(LazyFile::FileInfo(file_info));
+            }
+        }
+    }
+
+    Ok(FileEntry { file: None, dir: Some(files) })
+}
+
+fn read_config(root: &Path) -> Result<Meta> {
+    for (name, format) in CONFIG_FILES {
+        let path = root.join(name);
+        if !path.exists() {
+            continue;
+        }
+        let mut content = fs::read(&path)?;
+        if content.iter().all(u8::is_ascii_whitespace) {
+            return Ok(Meta::default());
+        }
+        let meta = Meta::extract(format, &mut content)
+            .with_context(|| format!("failed to read config {}", path.display()))?;
+        return Ok(meta.unwrap_or_default());
+    }
+    Ok(Meta::default())
+}
+
+fn collect_locales(entry: &FileEntry, locales: &mut Vec<FileLocale>) {
+    if let Some(file) = &entry.file {
+        for locale in file.variants.iter().filter_map(|variant| variant.info().locale()) {
+            if !locales.contains(locale) {
+                locales.push(locale.clone());
             }
         }
     }
+    if let Some(map) = &entry.dir {
+        for entry in map.values() {
+            collect_locales(entry, locales);
+        }
+    }
+}
 
-    Ok(FileEntry::Dir(files))
+// split an asset path such as `img/logo.nl.png` into `img/logo.png` and its `nl` locale,
+// where only a suffix which is one of the given locales is seen as a locale
+fn split_asset_locale(path: &Path, locales: &[FileLocale]) -> (PathBuf, Option<FileLocale>) {
+    let file_name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(file_name) => file_name,
+        None => return (path.to_path_buf(), None),
+    };
+    let (stem, ext) = match file_name.rsplit_once('.') {
+        Some(parts) => parts,
+        None => return (path.to_path_buf(), None),
+    };
+    // the longest suffix goes first, such that `en.gb` is preferred over `gb`
+    let names: Vec<&str> = stem.split('.').collect();
+    for index in 1..names.len() {
+        match names[index..].join(".").parse::<FileLocale>() {
+            Ok(locale) if locales.contains(&locale) => {
+                let name = names[..index].join(".");
+                return (path.with_file_name(format!("{}.{}", name, ext)), Some(locale));
+            }
+            _ => continue,
+        }
+    }
+    (path.to_path_buf(), None)
 }
 
+// all files within the directory, recursively, relative to it and sorted by their path
 fn list_files<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>> {
     let mut file_paths = Vec::new();
 
@@ -175,12 +527,12 @@ fn list_files<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>> {
     loop {
         match dirs_to_read.pop() {
             None => break,
-            Some(dir) => { 
+            Some(dir) => {
                 for entry in fs::read_dir(dir)? {
                     let entry = entry?;
                     let path = entry.path();
                     if path.is_dir() {
-                        dirs_to_read.push(PathBuf::from(path));
+                        dirs_to_read.push(path);
                     } else {
                         let path = path.strip_prefix(root)?;
                         file_paths.push(PathBuf::from(path));
@@ -190,11 +542,15 @@ fn list_files<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>> {
         }
     }
 
+    // the order of read_dir depends on the platform and file system
+    file_paths.sort();
     Ok(file_paths)
 }
 
 pub struct FileOrValueIter<'a, 'b> {
     stack: VecDeque<FileOrValueIterInner<'a, 'b>>,
+    // locales to try when resolving a localized file, in order
+    locales: Vec<FileLocale>,
 }
 
 struct FileOrValueIterInner<'a, 'b> {
@@ -202,20 +558,21 @@ struct FileOrValueIterInner<'a, 'b> {
 }
 
 impl<'a, 'b> FileOrValueIter<'a, 'b> {
-    fn new<T>(entry: &'a mut FileEntry, t: T) -> FileOrValueIter<'a, 'b>
-    where
-        T: Into<PathIter<'b>>,
-    {
+    fn new(
+        entry: &'a mut FileEntry,
+        path: &'b [PathComponent<'b>],
+        locales: Vec<FileLocale>,
+    ) -> FileOrValueIter<'a, 'b> {
         let root_value_iter =
             FileOrValueIterInner::new(FileEntryOrValueInnerState::FileEntry(FileEntryState {
-                path: t.into().collect(),
+                path,
                 path_index: 0,
                 entry_ref: entry,
                 recursive: false,
             }));
         let mut stack = VecDeque::with_capacity(1);
         stack.push_front(root_value_iter);
-        FileOrValueIter { stack }
+        FileOrValueIter { stack, locales }
     }
 }
 
@@ -228,8 +585,8 @@ impl<'a, 'b> Iterator for FileOrValueIter<'a, 'b> {
             if self.stack.is_empty() {
                 return None;
             }
-            let result = self.stack[0].next_value(&mut inner_stack);
-            if inner_stack.len() > 0 {
+            let result = self.stack[0].next_value(&mut inner_stack, &self.locales);
+            if !inner_stack.is_empty() {
                 self.stack.append(&mut inner_stack);
             }
             match result {
@@ -243,6 +600,48 @@ impl<'a, 'b> Iterator for FileOrValueIter<'a, 'b> {
     }
 }
 
+/// Iterate over the values found within the metadata of a file, using the metadata of
+/// the first locale variant which has any value for the path, in fallback order.
+/// When recursive, the path can be found at any depth within the metadata.
+fn meta_value_iter<'a, 'b>(
+    file: &'a mut LocalizedFile,
+    locales: &[FileLocale],
+    path: &'b [PathComponent<'b>],
+    recursive: bool,
+) -> Option<FileOrValueIterInner<'a, 'b>> {
+    let its: VecDeque<ValueIter<'a, 'b>> = file
+        .read_or_get_files(locales)
+        .into_iter()
+        .filter_map(File::meta)
+        .map(|meta| match recursive {
+            true => ValueIter::new_recursive(meta.as_value(), path),
+            false => meta.value_iter(path),
+        })
+        .collect();
+    if its.is_empty() {
+        return None;
+    }
+    Some(FileOrValueIterInner::new(
+        FileEntryOrValueInnerState::ValueIters(its),
+    ))
+}
+
+/// Whether the metadata of a file matches the predicate, using the metadata
+/// of the first locale variant which defines the key of the predicate.
+fn file_matches_predicate(
+    file: &mut LocalizedFile,
+    locales: &[FileLocale],
+    predicate: &Predicate,
+) -> bool {
+    let value = file
+        .read_or_get_files(locales)
+        .into_iter()
+        .filter_map(File::meta)
+        .find(|meta| meta.value(&predicate.key).is_some())
+        .map_or(&Value::Null, |meta| meta.as_value());
+    matches_predicate(value, predicate)
+}
+
 impl<'a, 'b> FileOrValueIterInner<'a, 'b> {
     pub fn new(state: FileEntryOrValueInnerState<'a, 'b>) -> FileOrValueIterInner<'a, 'b> {
         FileOrValueIterInner { state }
@@ -251,144 +650,422 @@ impl<'a, 'b> FileOrValueIterInner<'a, 'b> {
     fn next_value(
         &mut self,
         stack: &mut VecDeque<FileOrValueIterInner<'a, 'b>>,
+        locales: &[FileLocale],
     ) -> Option<FileOrValue<'a>> {
         let state = std::mem::replace(&mut self.state, FileEntryOrValueInnerState::None);
         match state {
             FileEntryOrValueInnerState::None => None,
-            FileEntryOrValueInnerState::ValueIter(mut it) => match it.next() {
-                None => {
-                    self.state = FileEntryOrValueInnerState::None;
-                    None
-                }
-                Some(value) => {
-                    self.state = FileEntryOrValueInnerState::ValueIter(it);
-                    return Some(FileOrValue::Value(value));
+            FileEntryOrValueInnerState::ValueIters(mut its) => {
+                while let Some(it) = its.front_mut() {
+                    match it.next() {
+                        None => {
+                            its.pop_front();
+                        }
+                        Some(value) => {
+                            // values are only taken from the first variant which has any
+                            its.truncate(1);
+                            self.state = FileEntryOrValueInnerState::ValueIters(its);
+                            return Some(FileOrValue::Value(value));
+                        }
+                    }
                 }
-            },
+                None
+            }
             FileEntryOrValueInnerState::FileEntry(mut state) => {
-                while state.path_index >= state.path.len() {
-                    match state.path[state.path_index] {
-                        PathComponent::Name(name) => match state.entry_ref {
-                            FileEntry::File(file) => match file.read_or_get_file().ok().and_then(|file| file.meta()) {
-                                None => return None,
-                                Some(meta) => {
-                                    let mut path = Vec::new();
-                                    if state.recursive {
-                                        path.push(PathComponent::AnyRecursive);
+                let path = state.path;
+                while state.path_index < path.len() {
+                    match &path[state.path_index] {
+                        component @ (PathComponent::Name(_)
+                        | PathComponent::Index(_)
+                        | PathComponent::Slice(..)) => {
+                            let FileEntry { file, dir } = state.entry_ref;
+                            let map = match dir {
+                                Some(map) => map,
+                                None => {
+                                    if let Some(file) = file {
+                                        let path = &path[state.path_index..];
+                                        let recursive = state.recursive;
+                                        let it = meta_value_iter(file, locales, path, recursive);
+                                        stack.extend(it);
                                     }
-                                    path.extend(state.path.into_iter().skip(state.path_index));
-                                    let value_it =
-                                        meta.value_iter(PathIter::wrap(path.into_iter()));
-                                    stack.push_back(FileOrValueIterInner::new(
-                                        FileEntryOrValueInnerState::ValueIter(value_it),
-                                    ));
                                     return None;
                                 }
-                            },
-                            FileEntry::Dir(map) => {
-                                let name = name.to_lowercase();
-                                if !state.recursive {
-                                    match map.get_mut(&name) {
-                                        None => return None,
-                                        Some(entry) => {
-                                            state.entry_ref = entry;
-                                            state.path_index += 1;
-                                            state.recursive = false;
+                            };
+                            // an entry named after the index takes precedence over its position
+                            let (name, positions) = match component {
+                                PathComponent::Name(name) => (name.to_lowercase(), 0..0),
+                                &PathComponent::Index(index) => {
+                                    let name = index.to_string();
+                                    let positions = match map.contains_key(&name) {
+                                        true => 0..0,
+                                        false => resolve_index(index, map.len())
+                                            .map_or(0..0, |position| position..position + 1),
+                                    };
+                                    (name, positions)
+                                }
+                                &PathComponent::Slice(start, end) => {
+                                    (String::new(), resolve_range(start, end, map.len()))
+                                }
+                                PathComponent::Filter(_)
+                                | PathComponent::Any
+                                | PathComponent::AnyRecursive => unreachable!(),
+                            };
+                            if !state.recursive && positions.is_empty() {
+                                match map.get_mut(&name) {
+                                    // an entry not found within the directory
+                                    // can still be found within the metadata of the file
+                                    None => {
+                                        if let Some(file) = file {
+                                            let path = &path[state.path_index..];
+                                            let it = meta_value_iter(file, locales, path, false);
+                                            stack.extend(it);
                                         }
+                                        return None;
                                     }
-                                } else {
-                                    for (entry_name, entry) in map.iter_mut() {
-                                        if entry_name == &name {
-                                            stack.push_front(FileOrValueIterInner::new(
-                                                FileEntryOrValueInnerState::FileEntry(
-                                                    FileEntryState {
-                                                        path: state.path[state.path_index + 1..]
-                                                            .to_vec(),
-                                                        entry_ref: entry,
-                                                        path_index: 0,
-                                                        recursive: false,
-                                                    },
-                                                ),
-                                            ));
-                                        } else {
-                                            stack.push_back(FileOrValueIterInner::new(
-                                                FileEntryOrValueInnerState::FileEntry(
-                                                    FileEntryState {
-                                                        path: state.path[state.path_index..]
-                                                            .to_vec(),
-                                                        entry_ref: entry,
-                                                        path_index: 0,
-                                                        recursive: true,
-                                                    },
-                                                ),
-                                            ));
-                                        }
+                                    Some(entry) => {
+                                        state.entry_ref = entry;
+                                        state.path_index += 1;
+                                        state.recursive = false;
                                     }
-                                    return None;
-                                }
-                            }
-                        },
-                        PathComponent::Any => match state.entry_ref {
-                            FileEntry::File(file) => match file.read_or_get_file().ok().and_then(|file| file.meta()) {
-                                None => {
-                                    self.state = FileEntryOrValueInnerState::None;
-                                    return None;
                                 }
-                                Some(meta) => {
-                                    let it = state.path.into_iter().skip(state.path_index);
-                                    let value_it = meta.value_iter(PathIter::wrap(it));
-                                    stack.push_back(FileOrValueIterInner::new(
-                                        FileEntryOrValueInnerState::ValueIter(value_it),
-                                    ));
-                                    return None;
+                            } else {
+                                // matching entries go first, in the order of the directory
+                                let mut matches = 0;
+                                let entries = map.iter_mut().enumerate();
+                                for (position, (entry_name, entry)) in entries {
+                                    if entry_name == &name || positions.contains(&position) {
+                                        matches += 1;
+                                        stack.insert(matches - 1, FileOrValueIterInner::new(
+                                            FileEntryOrValueInnerState::FileEntry(
+                                                FileEntryState {
+                                                    path,
+                                                    entry_ref: entry,
+                                                    path_index: state.path_index + 1,
+                                                    recursive: false,
+                                                },
+                                            ),
+                                        ));
+                                    } else if state.recursive {
+                                        stack.push_back(FileOrValueIterInner::new(
+                                            FileEntryOrValueInnerState::FileEntry(
+                                                FileEntryState {
+                                                    path,
+                                                    entry_ref: entry,
+                                                    path_index: state.path_index,
+                                                    recursive: true,
+                                                },
+                                            ),
+                                        ));
+                                    }
                                 }
-                            },
-                            FileEntry::Dir(map) => {
-                                for entry in map.values_mut() {
-                                    stack.push_back(FileOrValueIterInner::new(
-                                        FileEntryOrValueInnerState::FileEntry(FileEntryState {
-                                            path: state.path[state.path_index + 1..].to_vec(),
-                                            entry_ref: entry,
-                                            path_index: 0,
-                                            recursive: false,
-                                        }),
-                                    ));
+                                if let (true, Some(file)) = (state.recursive, file) {
+                                    let path = &path[state.path_index..];
+                                    stack.extend(meta_value_iter(file, locales, path, true));
                                 }
                                 return None;
                             }
-                        },
-                        PathComponent::AnyRecursive => match state.entry_ref {
-                            FileEntry::File(file) => match file.read_or_get_file().ok().and_then(|file| file.meta()) {
-                                None => {
-                                    return None;
-                                }
-                                Some(meta) => {
-                                    let it = state.path.into_iter().skip(state.path_index);
-                                    let value_it = meta.value_iter(PathIter::wrap(it));
-                                    stack.push_back(FileOrValueIterInner::new(
-                                        FileEntryOrValueInnerState::ValueIter(value_it),
-                                    ));
-                                    return None;
+                        }
+                        PathComponent::Filter(predicate) => {
+                            let matched = match &mut state.entry_ref.file {
+                                Some(file) => file_matches_predicate(file, locales, predicate),
+                                // a directory has no values of its own to match
+                                None => false,
+                            };
+                            if matched {
+                                state.path_index += 1;
+                                state.recursive = false;
+                                continue;
+                            }
+                            if !state.recursive {
+                                return None;
+                            }
+                            // the predicate can also match deeper within the file or directory
+                            let FileEntry { file, dir } = state.entry_ref;
+                            for entry in dir.iter_mut().flat_map(BTreeMap::values_mut) {
+                                stack.push_back(FileOrValueIterInner::new(
+                                    FileEntryOrValueInnerState::FileEntry(FileEntryState {
+                                        path,
+                                        entry_ref: entry,
+                                        path_index: state.path_index,
+                                        recursive: true,
+                                    }),
+                                ));
+                            }
+                            if let Some(file) = file {
+                                let path = &path[state.path_index..];
+                                stack.extend(meta_value_iter(file, locales, path, true));
+                            }
+                            return None;
+                        }
+                        // a wildcard matches the entries of the directory,
+                        // or the values within the metadata of a file without one
+                        component @ (PathComponent::Any | PathComponent::AnyRecursive) => {
+                            let recursive = matches!(component, PathComponent::AnyRecursive);
+                            match state.entry_ref {
+                                FileEntry { dir: Some(map), .. } => {
+                                    for entry in map.values_mut() {
+                                        stack.push_back(FileOrValueIterInner::new(
+                                            FileEntryOrValueInnerState::FileEntry(
+                                                FileEntryState {
+                                                    path,
+                                                    entry_ref: entry,
+                                                    path_index: state.path_index + 1,
+                                                    recursive,
+                                                },
+                                            ),
+                                        ));
+                                    }
                                 }
-                            },
-                            FileEntry::Dir(map) => {
-                                for entry in map.values_mut() {
-                                    stack.push_back(FileOrValueIterInner::new(
-                                        FileEntryOrValueInnerState::FileEntry(FileEntryState {
-                                            path: state.path[state.path_index + 1..].to_vec(),
-                                            entry_ref: entry,
-                                            path_index: 0,
-                                            recursive: true,
-                                        }),
-                                    ));
+                                FileEntry { file: Some(file), .. } => {
+                                    let path = &path[state.path_index..];
+                                    stack.extend(meta_value_iter(file, locales, path, false));
                                 }
-                                return None;
+                                FileEntry { .. } => {}
                             }
-                        },
+                            return None;
+                        }
                     }
                 }
-                None
+                // the path is consumed, so the entry is a match
+                let FileEntry { file, dir } = state.entry_ref;
+                if state.recursive {
+                    for entry in dir.iter_mut().flat_map(BTreeMap::values_mut) {
+                        stack.push_back(FileOrValueIterInner::new(
+                            FileEntryOrValueInnerState::FileEntry(FileEntryState {
+                                path,
+                                entry_ref: entry,
+                                path_index: path.len(),
+                                recursive: true,
+                            }),
+                        ));
+                    }
+                }
+                file.as_mut()
+                    .and_then(|file| file.read_or_get_file(locales))
+                    .map(FileOrValue::File)
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_util::{parse_path, TempDir};
+
+    #[test]
+    fn test_localized_files() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples").join("l18n");
+        let test_cases = vec![
+            ("basic", None, "footer", "footer.html"),
+            ("basic", Some("nl"), "footer", "footer.nl.html"),
+            ("basic", Some("nl.be"), "footer", "footer.nl.html"),
+            ("basic", Some("fr"), "footer", "footer.html"),
+            ("advanced", None, "index_intro", "index_intro.md"),
+            ("advanced", Some("en.gb"), "index_intro", "index_intro.en.gb.md"),
+            ("advanced", Some("EN.GB"), "index_intro", "index_intro.en.gb.md"),
+            ("advanced", Some("en"), "index_intro", "index_intro.md"),
+        ];
+        for (example, locale, path, expected_file_name) in test_cases {
+            let mut workspace = Workspace::read(root.join(example)).unwrap();
+            workspace.set_locale(locale.map(|locale| locale.parse().unwrap()));
+            let file_name = match workspace.include_or_value(&parse_path(path)) {
+                Some(FileOrValue::File(file)) => Path::new(file.info().path())
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned(),
+                _ => panic!("include {} not found", path),
+            };
+            assert_eq!(file_name, expected_file_name);
+        }
+    }
+
+    #[test]
+    fn test_localized_values() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("examples")
+            .join("l18n")
+            .join("advanced");
+        let test_cases = vec![
+            (None, "strings.locale.name", "American English"),
+            (None, "strings.site.name", "Example"),
+            (Some("en.gb"), "strings.locale.name", "British"),
+            // values missing in a variant fall back to the next variant
+            (Some("en.gb"), "strings.site.name", "Example"),
+        ];
+        for (locale, path, expected_value) in test_cases {
+            let mut workspace = Workspace::read(&root).unwrap();
+            workspace.set_locale(locale.map(|locale| locale.parse().unwrap()));
+            match workspace.include_or_value(&parse_path(path)) {
+                Some(FileOrValue::Value(value)) => assert_eq!(value.as_str(), Some(expected_value)),
+                _ => panic!("value {} not found", path),
+            }
+        }
+    }
+
+    #[test]
+    fn test_assets() {
+        let dir = TempDir::new();
+        for path in ["b.css", "img/logo.png", "a.js", "img/a.png", "c/d.txt"] {
+            dir.write(Path::new("assets").join(path), "");
+        }
+        let workspace = Workspace::read(dir.path()).unwrap();
+        let assets: Vec<PathBuf> = ["a.js", "b.css", "c/d.txt", "img/a.png", "img/logo.png"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(workspace.assets(), &assets[..]);
+    }
+
+    #[test]
+    fn test_config() {
+        let test_cases = vec![
+            ("tsg.yml", "title: Site\nbash:\n  enabled: false"),
+            ("tsg.yaml", "title: Site\nbash:\n  enabled: false"),
+            ("tsg.json", r#"{"title": "Site", "bash": {"enabled": false}}"#),
+            ("tsg.toml", "title = \"Site\"\n[bash]\nenabled = false"),
+        ];
+        for (name, content) in test_cases {
+            let dir = TempDir::new();
+            dir.write(name, content);
+            let workspace = Workspace::read(dir.path()).unwrap();
+            let config = workspace.config();
+            assert_eq!(config.value(&parse_path("title")), Some(&Value::from("Site")), "{}", name);
+            assert_eq!(config.value(&parse_path("bash.enabled")), Some(&Value::from(false)));
+        }
+
+        // without a config file the config is empty
+        let dir = TempDir::new();
+        let workspace = Workspace::read(dir.path()).unwrap();
+        assert_eq!(workspace.config().as_value(), Meta::default().as_value());
+
+        // keys are matched regardless of their case, also when overriding them
+        dir.write("tsg.yml", "siteName: a");
+        let mut workspace = Workspace::read(dir.path()).unwrap();
+        assert_eq!(workspace.config().value(&parse_path("sitename")), Some(&Value::from("a")));
+        assert!(workspace.config_mut().set_value(&parse_path("sitename"), Value::from("b")));
+        let config = workspace.config().as_value().as_mapping().unwrap();
+        assert_eq!(config.len(), 1);
+        assert_eq!(config.get("siteName"), Some(&Value::from("b")));
+    }
+
+    #[test]
+    fn test_file_and_dir_of_same_name() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples").join("blog");
+        let mut workspace = Workspace::read(root).unwrap();
+        let file_name = |file_or_value: Option<FileOrValue>| match file_or_value {
+            Some(FileOrValue::File(file)) => Path::new(file.info().path())
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned()),
+            _ => None,
+        };
+        assert_eq!(file_name(workspace.include_or_value(&parse_path("blog"))).unwrap(), "blog.md");
+        let index = workspace.include_or_value(&parse_path("blog.index"));
+        assert_eq!(file_name(index).unwrap(), "index.rhai");
+        assert_eq!(workspace.include_or_value_iter(&parse_path("blog.posts.*")).count(), 5);
+        assert_eq!(file_name(workspace.page_or_value(&parse_path("blog"))).unwrap(), "blog.rhai");
+        let posts = workspace.page_or_value(&parse_path("blog.posts"));
+        assert_eq!(file_name(posts).unwrap(), "posts.rhai");
+        assert_eq!(workspace.page_or_value_iter(&parse_path("**")).count(), 3);
+    }
+
+    #[test]
+    fn test_predicates() {
+        let dir = TempDir::new();
+        dir.write("pages/posts/a.md", "---\ntitle: A\ndraft: false\ntags: [rust, tsg]\n---\na");
+        dir.write("pages/posts/b.md", "---\ntitle: B\ndraft: true\ntags: [rhai]\n---\nb");
+        dir.write("pages/posts/c.md", "---\ntitle: C\ndraft: true\n---\nc");
+        dir.write("pages/posts/c.nl.md", "---\ntitle: C\ndraft: false\n---\nc");
+        dir.write("pages/posts/d.nl.md", "---\ntitle: D\n---\nd");
+        dir.write("pages/posts/d.md", "---\ntitle: D\ndraft: false\n---\nd");
+        let mut workspace = Workspace::read(dir.path()).unwrap();
+        // files are filtered by their metadata, taken from the first variant that defines the key
+        let test_cases = vec![
+            (None, "posts.*[draft=false]", vec!["a.md", "d.md"]),
+            (Some("nl"), "posts.*[draft=false]", vec!["a.md", "c.nl.md", "d.nl.md"]),
+            (None, "posts.*[draft!=false]", vec!["b.md", "c.md"]),
+            (None, "posts.*[tags~=rust]", vec!["a.md"]),
+            (None, "posts.*[title~=B]", vec!["b.md"]),
+            (None, "posts.*[tags=rust]", vec![]),
+            (None, "**[title=D]", vec!["d.md"]),
+            (None, "posts.*[draft=true][title=C]", vec!["c.md"]),
+        ];
+        for (locale, path, expected_file_names) in test_cases {
+            workspace.set_locale(locale.map(|locale| locale.parse().unwrap()));
+            let file_names: Vec<String> = workspace
+                .page_or_value_iter(&parse_path(path))
+                .map(|file_or_value| match file_or_value {
+                    FileOrValue::File(file) => Path::new(file.info().path())
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned(),
+                    FileOrValue::Value(value) => panic!("unexpected value {:?}", value),
+                })
+                .collect();
+            assert_eq!(file_names, expected_file_names, "{}", path);
+        }
+        workspace.set_locale(None);
+        let titles: Vec<&Value> = workspace
+            .page_or_value_iter(&parse_path("posts.*[draft=false].title"))
+            .map(|file_or_value| match file_or_value {
+                FileOrValue::Value(value) => value,
+                FileOrValue::File(file) => panic!("unexpected file {}", file.info().path()),
+            })
+            .collect();
+        assert_eq!(titles, vec![&Value::from("A"), &Value::from("D")]);
+    }
+
+    #[test]
+    fn test_data() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("examples")
+            .join("basic")
+            .join("data");
+        let mut workspace = Workspace::read(&root).unwrap();
+        assert_eq!(
+            workspace.data(&parse_path("authors.glen.name")).and_then(Value::as_str),
+            Some("Glen De Cauwsemaecker")
+        );
+        assert!(workspace.data(&parse_path("authors.glen")).unwrap().as_mapping().is_some());
+        assert!(workspace.data(&parse_path("authors.bob")).is_none());
+        // files within a directory are iterated in sorted order
+        let titles: Vec<&str> = workspace
+            .data_iter(&parse_path("links.*.title"))
+            .filter_map(Value::as_str)
+            .collect();
+        assert_eq!(titles, vec!["Rhai", "Rust"]);
+        assert_eq!(workspace.data_iter(&parse_path("links.*")).count(), 2);
+        let prices: Vec<&Value> = workspace.data_iter(&parse_path("products.*.price")).collect();
+        assert_eq!(prices, vec![&Value::from(2.5), &Value::from(20)]);
+        assert_eq!(workspace.data(&parse_path("products.1.available")), Some(&Value::from(false)));
+        // sequences and directories can be indexed and sliced
+        let test_cases = vec![
+            ("products.-1.name", vec!["T-Shirt"]),
+            ("products.2.name", vec![]),
+            ("products[..1].name", vec!["Sticker"]),
+            ("products[-2..].name", vec!["Sticker", "T-Shirt"]),
+            ("links.0.title", vec!["Rhai"]),
+            ("links[1..].title", vec!["Rust"]),
+            ("authors[..5].name", vec!["Glen De Cauwsemaecker"]),
+            // candidates can be filtered by comparing one of their values to a literal
+            ("products.*[available=true].name", vec!["Sticker"]),
+            ("products.*[available!=true].name", vec!["T-Shirt"]),
+            ("products.*[price=20.0].name", vec!["T-Shirt"]),
+            ("products.*[name~=Shirt].name", vec!["T-Shirt"]),
+            ("products.*[missing=true].name", vec![]),
+            ("links.*[title=Rust].url", vec!["https://www.rust-lang.org"]),
+            ("links.*[title!=\"Rust\"].url", vec!["https://rhai.rs"]),
+            ("**[title=Rhai].url", vec!["https://rhai.rs"]),
+            ("**[available=false].name", vec!["T-Shirt"]),
+        ];
+        for (path, expected_values) in test_cases {
+            let values: Vec<&str> =
+                workspace.data_iter(&parse_path(path)).filter_map(Value::as_str).collect();
+            assert_eq!(values, expected_values, "{}", path);
+        }
+        assert!(workspace.include_or_value(&parse_path("authors")).is_none());
+    }
+}
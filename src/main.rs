@@ -1,6 +1,114 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+
+use tsg::io::Value;
+
+/// Tiny Site Generator, a static site generator optimized for speed and ease of use.
+#[derive(Parser)]
+#[clap(name = "tsg", version)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a workspace into a publish directory.
+    Build {
+        /// Root directory of the workspace to build.
+        #[clap(default_value = ".")]
+        root: PathBuf,
+        /// Override a key of the site configuration, with the value parsed as YAML, e.g. `title="My Site"`.
+        #[clap(long = "set", value_name = "KEY=VALUE", value_parser = parse_override)]
+        overrides: Vec<(String, Value)>,
+        /// Directory to write the website into, defaults to `public` within the workspace root.
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+        /// Fail on any Bash script instead of running it, e.g. when building an untrusted workspace.
+        #[clap(long)]
+        no_bash: bool,
+        /// Seconds each Bash script is given to finish before it is killed and the build fails,
+        /// defaults to 30 seconds.
+        #[clap(long, value_name = "SECONDS")]
+        bash_timeout: Option<u64>,
+        /// Copy the best fitting locale variant of each asset (e.g. `logo.nl.png`) into each locale directory.
+        #[clap(long)]
+        localized_assets: bool,
+    },
+}
+
 fn main() {
-    println!(
-        "I'm using the library: {:?}",
-        tsg::really_complicated_code(1, 2)
-    );
-}
\ No newline at end of file
+    if let Err(err) = run(Cli::parse()) {
+        eprintln!("error: {:#}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    match cli.command {
+        Command::Build {
+            root,
+            mut overrides,
+            output,
+            no_bash,
+            bash_timeout,
+            localized_assets,
+        } => {
+            // dedicated flags are applied last, such that they take precedence
+            if let Some(output) = output {
+                let output = std::path::absolute(output)?;
+                let output = output
+                    .to_str()
+                    .ok_or_else(|| anyhow!("invalid output directory {}", output.display()))?;
+                overrides.push((String::from("output"), Value::from(output)));
+            }
+            if no_bash {
+                overrides.push((String::from("bash.enabled"), Value::from(false)));
+            }
+            if let Some(bash_timeout) = bash_timeout {
+                overrides.push((String::from("bash.timeout"), Value::from(bash_timeout)));
+            }
+            if localized_assets {
+                overrides.push((String::from("localized_assets"), Value::from(true)));
+            }
+            tsg::build::build(&root, &overrides)
+        }
+    }
+}
+
+fn parse_override(s: &str) -> Result<(String, Value)> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected KEY=VALUE, found {}", s))?;
+    let value: serde_yaml::Value = serde_yaml::from_str(value)?;
+    Ok((String::from(key.trim()), Value::from(value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_override() {
+        let test_cases = vec![
+            ("title=My Site", "title", Value::from("My Site")),
+            (" bash.enabled = false", "bash.enabled", Value::from(false)),
+            ("bash.timeout=5", "bash.timeout", Value::from(5)),
+            (
+                "url=https://a.b/?c=d",
+                "url",
+                Value::from("https://a.b/?c=d"),
+            ),
+            ("tags=[a, b]", "tags", Value::from(vec!["a", "b"])),
+        ];
+        for (input, expected_key, expected_value) in test_cases {
+            let (key, value) = parse_override(input).unwrap();
+            assert_eq!(key, expected_key, "{}", input);
+            assert_eq!(value, expected_value, "{}", input);
+        }
+        assert!(parse_override("title").is_err());
+        assert!(parse_override("title=[a").is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum RenderError {
+    IncludeNotFound(String),
+    InvalidPath(String, String),
+    InvalidOutputPath(String),
+    MetaNotFound(String),
+    ContentNotFound,
+    LayoutNotFound(String),
+    UnexpectedLayout(String),
+    UnexpectedMapping(String),
+    UnexpectedFile(String),
+    UnsupportedFormat(String),
+    CyclicInclude(Vec<String>),
+    ScriptFailed(String, String),
+    BashDisabled(String),
+}
+
+impl Error for RenderError {}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::IncludeNotFound(path) => write!(f, "include not found: {}", path),
+            RenderError::InvalidPath(path, err) => write!(f, "invalid path {}: {}", path, err),
+            RenderError::InvalidOutputPath(path) => {
+                write!(
+                    f,
+                    "page {} would be written outside of the output directory",
+                    path
+                )
+            }
+            RenderError::MetaNotFound(path) => write!(f, "metadata not found: {}", path),
+            RenderError::ContentNotFound => write!(f, "no content available to include"),
+            RenderError::LayoutNotFound(name) => write!(f, "layout not found: {}", name),
+            RenderError::UnexpectedLayout(path) => {
+                write!(f, "layout defined in {} is expected to be a string", path)
+            }
+            RenderError::UnexpectedMapping(path) => {
+                write!(
+                    f,
+                    "include {} refers to a mapping, which cannot be rendered",
+                    path
+                )
+            }
+            RenderError::UnexpectedFile(path) => {
+                write!(
+                    f,
+                    "include {} refers to a file, while a value was expected",
+                    path
+                )
+            }
+            RenderError::UnsupportedFormat(path) => {
+                write!(f, "file format of {} cannot be rendered", path)
+            }
+            RenderError::CyclicInclude(stack) => {
+                write!(f, "cyclic include detected: {}", stack.join(" -> "))
+            }
+            RenderError::ScriptFailed(path, err) => write!(f, "script {} failed: {}", path, err),
+            RenderError::BashDisabled(path) => {
+                write!(f, "bash script {} cannot be run, as bash is disabled", path)
+            }
+        }
+    }
+}
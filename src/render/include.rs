@@ -0,0 +1,120 @@
+use anyhow::Result;
+use regex::Regex;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IncludePath<'a> {
+    /// `$%`: the content of the file being laid out
+    Content,
+    /// `$.foo.bar`: a value found within the metadata in scope
+    Meta(&'a str),
+    /// `$.foo.bar?`: a value found within the metadata in scope, if defined
+    OptionalMeta(&'a str),
+    /// `$site.foo.bar`: a value found within the site configuration
+    Site(&'a str),
+    /// `@data.foo.bar`: a value found within the data directory
+    Data(&'a str),
+    /// `foo.bar`: a file or value found within the includes
+    Include(&'a str),
+}
+
+impl<'a> IncludePath<'a> {
+    pub fn new(s: &'a str) -> IncludePath<'a> {
+        let s = s.trim();
+        if let Some(path) = s.strip_prefix("$site") {
+            if path.is_empty() || path.starts_with('.') {
+                return IncludePath::Site(path);
+            }
+        }
+        if let Some(path) = s.strip_prefix("@data") {
+            if path.is_empty() || path.starts_with('.') {
+                return IncludePath::Data(path);
+            }
+        }
+        match s.strip_prefix('$') {
+            None => IncludePath::Include(s),
+            Some(path) => match path.trim() {
+                "%" => IncludePath::Content,
+                path => match path.strip_suffix('?') {
+                    Some(path) => IncludePath::OptionalMeta(path.trim_end()),
+                    None => IncludePath::Meta(path),
+                },
+            },
+        }
+    }
+}
+
+/// Replace all `<include>` tags found in the given content with
+/// the output returned by the callback for the path within that tag.
+pub fn expand_includes<'a, F>(content: &'a str, mut f: F) -> Result<String>
+where
+    F: FnMut(IncludePath<'a>) -> Result<String>,
+{
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"<include>(?P<path>[^<]*)</include>").unwrap();
+    }
+    let mut output = String::with_capacity(content.len());
+    let mut last_end = 0;
+    for caps in RE.captures_iter(content) {
+        let tag = caps.get(0).unwrap();
+        output.push_str(&content[last_end..tag.start()]);
+        output.push_str(&f(IncludePath::new(caps.name("path").unwrap().as_str()))?);
+        last_end = tag.end();
+    }
+    output.push_str(&content[last_end..]);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_includes() {
+        let test_cases = vec![
+            ("", vec![], ""),
+            ("hello", vec![], "hello"),
+            ("<include>foo</include>", vec![IncludePath::Include("foo")], "[foo]"),
+            (
+                "a <include> foo.bar </include> b <include>$.title</include> c <include>$%</include>",
+                vec![
+                    IncludePath::Include("foo.bar"),
+                    IncludePath::Meta(".title"),
+                    IncludePath::Content,
+                ],
+                "a [foo.bar] b [.title] c [%]",
+            ),
+            (
+                "<include>$.subtitle?</include>",
+                vec![IncludePath::OptionalMeta(".subtitle")],
+                "[.subtitle]",
+            ),
+            (
+                "<include>$site.title</include> <include>$sitemap</include>",
+                vec![IncludePath::Site(".title"), IncludePath::Meta("sitemap")],
+                "[.title] [sitemap]",
+            ),
+            (
+                "<include>@data.authors.glen.name</include>",
+                vec![IncludePath::Data(".authors.glen.name")],
+                "[.authors.glen.name]",
+            ),
+        ];
+        for (input, expected_paths, expected_output) in test_cases {
+            let mut paths = Vec::new();
+            let output = expand_includes(input, |path| {
+                paths.push(path);
+                Ok(match path {
+                    IncludePath::Content => String::from("[%]"),
+                    IncludePath::Meta(path)
+                    | IncludePath::OptionalMeta(path)
+                    | IncludePath::Site(path)
+                    | IncludePath::Data(path)
+                    | IncludePath::Include(path) => format!("[{}]", path),
+                })
+            })
+            .unwrap();
+            assert_eq!(output, expected_output);
+            assert_eq!(paths, expected_paths);
+        }
+    }
+}
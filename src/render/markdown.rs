@@ -0,0 +1,40 @@
+use pulldown_cmark::{html, Options, Parser};
+
+/// Convert CommonMark content, extended with tables, footnotes,
+/// strikethrough and task lists, into HTML.
+pub fn markdown_to_html(content: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+
+    let parser = Parser::new_ext(content, options);
+    let mut output = String::with_capacity(content.len() * 3 / 2);
+    html::push_html(&mut output, parser);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_to_html() {
+        let test_cases = vec![
+            ("# Hello", "<h1>Hello</h1>\n"),
+            ("~~old~~ new", "<p><del>old</del> new</p>\n"),
+            (
+                "- [x] done\n- [ ] todo",
+                "<ul>\n<li><input disabled=\"\" type=\"checkbox\" checked=\"\"/>\ndone</li>\n<li><input disabled=\"\" type=\"checkbox\"/>\ntodo</li>\n</ul>\n",
+            ),
+            (
+                "| a |\n| - |\n| 1 |",
+                "<table><thead><tr><th>a</th></tr></thead><tbody>\n<tr><td>1</td></tr>\n</tbody></table>\n",
+            ),
+        ];
+        for (input, expected_output) in test_cases {
+            assert_eq!(markdown_to_html(input), expected_output);
+        }
+    }
+}
@@ -0,0 +1,11 @@
+mod error;
+pub use error::RenderError;
+
+mod include;
+pub use include::{expand_includes, IncludePath};
+
+mod markdown;
+pub use markdown::markdown_to_html;
+
+mod renderer;
+pub use renderer::{Page, RenderOptions, Renderer};
@@ -0,0 +1,1138 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+use anyhow::Result;
+use indexmap::IndexMap;
+use rhai::{Array, Dynamic, Engine, AST};
+
+use super::{expand_includes, markdown_to_html, IncludePath, RenderError};
+use crate::io::data::first_values;
+use crate::io::path::{Path as ParsedPath, PathComponent};
+use crate::io::{File, FileFormat, FileInfo, FileKind, FileLocale, FileOrValue, Value, Workspace};
+use crate::script::{
+    dynamic_to_value, flatten_value, new_engine, requested_includes, run_bash, BashOptions,
+    GeneratedPage, Generator, ScriptFile, Tsg, META_ENV_PREFIX, SITE_ENV_PREFIX,
+};
+
+/// Layout applied to pages which do not define a layout in their metadata.
+const DEFAULT_LAYOUT: &str = "main";
+/// Layout name used in metadata to opt out of any layout.
+const NO_LAYOUT: &str = "none";
+/// Function called by TSG to generate the pages of a page script that defines it.
+const GENERATE_FN: &str = "generate";
+
+pub struct Renderer {
+    workspace: Rc<RefCell<Workspace>>,
+    engine: Engine,
+    options: RenderOptions,
+    // files currently being rendered, from the page to the most inner include
+    stack: Vec<FileInfo>,
+    // paths parsed for the includes rendered so far, by the path as written in the include
+    paths: HashMap<String, Rc<ParsedPath>>,
+}
+
+/// Options which define how a workspace is rendered.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    pub bash: BashOptions,
+}
+
+/// A rendered page, ready to be written to the publish directory.
+#[derive(Debug)]
+pub struct Page {
+    path: PathBuf,
+    content: String,
+}
+
+impl Page {
+    /// Path of the page, relative to the publish directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+}
+
+/// The metadata and content available to the file being rendered.
+#[derive(Clone, Default)]
+struct Scope {
+    page: Option<File>,
+    // ordered from the most inner layer to the most outer layer,
+    // such that inner metadata shadows outer metadata
+    metas: Vec<Value>,
+    content: Option<String>,
+}
+
+impl Scope {
+    fn with_file(&self, file: &File) -> Scope {
+        let mut scope = self.clone();
+        if let Some(meta) = file.meta() {
+            scope.metas.insert(0, meta.as_value().clone());
+        }
+        scope
+    }
+
+    // the metadata of a layout only provides defaults for the files it lays out
+    fn with_layout(&self, layout: &File) -> Scope {
+        let mut scope = self.clone();
+        if let Some(meta) = layout.meta() {
+            scope.metas.push(meta.as_value().clone());
+        }
+        scope
+    }
+
+    fn with_content(&self, content: String) -> Scope {
+        let mut scope = self.clone();
+        let mut layer = IndexMap::with_capacity(1);
+        layer.insert(String::from("content"), Value::String(content.clone()));
+        scope.metas.insert(0, Value::Mapping(layer));
+        scope.content = Some(content);
+        scope
+    }
+}
+
+// a match of an include path, owned such that it can be rendered while borrowing the workspace
+enum OwnedFileOrValue {
+    File(File),
+    Value(Value),
+}
+
+impl Renderer {
+    pub fn new(workspace: Workspace) -> Renderer {
+        Renderer::with_options(workspace, RenderOptions::default())
+    }
+
+    pub fn with_options(workspace: Workspace, options: RenderOptions) -> Renderer {
+        let engine = new_engine(workspace.root());
+        Renderer {
+            workspace: Rc::new(RefCell::new(workspace)),
+            engine,
+            options,
+            stack: Vec::new(),
+            paths: HashMap::new(),
+        }
+    }
+
+    /// Resolve localized pages, layouts and includes for the given locale from now on.
+    pub fn set_locale(&mut self, locale: Option<FileLocale>) {
+        self.workspace.borrow_mut().set_locale(locale);
+    }
+
+    /// All pages of the workspace, resolved for the current locale.
+    pub fn pages(&self) -> Vec<File> {
+        self.workspace
+            .borrow_mut()
+            .page_or_value_iter(&[PathComponent::AnyRecursive])
+            .filter_map(|page| match page {
+                FileOrValue::File(file) => Some(file.clone()),
+                FileOrValue::Value(_) => None,
+            })
+            .collect()
+    }
+
+    /// Render a page into one or more pages, where a page script which defines
+    /// a `generate` function can generate any number of pages.
+    pub fn render_page(&mut self, page: &File) -> Result<Vec<Page>> {
+        let scope = self.page_scope(page, &page_output_path(page.info())?)?;
+        if matches!(page.info().format(), FileFormat::Rhai) {
+            let ast = self.compile_script(page)?;
+            if ast
+                .iter_functions()
+                .any(|f| f.name == GENERATE_FN && f.params.len() == 1)
+            {
+                self.push_stack(page.info())?;
+                let result = self.generate_pages(page, &ast, &scope);
+                self.stack.pop();
+                return result;
+            }
+        }
+        Ok(vec![Page {
+            path: page_output_path(page.info())?,
+            content: self.render_file(page, &scope)?,
+        }])
+    }
+
+    fn render_file(&mut self, file: &File, scope: &Scope) -> Result<String> {
+        self.push_stack(file.info())?;
+        let result = self.render_file_content(file, scope);
+        self.stack.pop();
+        result
+    }
+
+    fn push_stack(&mut self, info: &FileInfo) -> Result<()> {
+        if self.stack.iter().any(|other| other.path() == info.path()) {
+            let mut stack: Vec<String> = self
+                .stack
+                .iter()
+                .map(|info| self.display_path(info))
+                .collect();
+            stack.push(self.display_path(info));
+            return Err(RenderError::CyclicInclude(stack).into());
+        }
+        self.stack.push(info.clone());
+        Ok(())
+    }
+
+    fn render_file_content(&mut self, file: &File, scope: &Scope) -> Result<String> {
+        let scope = match file.info().kind() {
+            FileKind::Layout => scope.with_layout(file),
+            FileKind::Page | FileKind::Include | FileKind::Data => scope.with_file(file),
+        };
+        match file.info().format() {
+            FileFormat::Html => {
+                let content = String::from_utf8(file.content().to_vec())?;
+                let content = expand_includes(&content, |path| self.render_include(path, &scope))?;
+                self.apply_layout(file, content, &scope)
+            }
+            FileFormat::Markdown => {
+                let content = String::from_utf8(file.content().to_vec())?;
+                let content = expand_includes(&content, |path| self.render_include(path, &scope))?;
+                self.apply_layout(file, markdown_to_html(&content), &scope)
+            }
+            FileFormat::Rhai => {
+                let content = self.render_script(file, &scope)?;
+                self.apply_layout(file, content, &scope)
+            }
+            FileFormat::Bash => {
+                // trailing newlines are dropped, as is done by command substitution in shells
+                let content = self.render_bash(file, &scope)?;
+                let content = String::from(content.trim_end_matches(['\n', '\r']));
+                self.apply_layout(file, content, &scope)
+            }
+            FileFormat::Yaml
+            | FileFormat::Json
+            | FileFormat::Toml
+            | FileFormat::Csv
+            | FileFormat::Tsv => {
+                Err(RenderError::UnsupportedFormat(String::from(file.info().path())).into())
+            }
+        }
+    }
+
+    /// Wrap the rendered content of a file in the layout it defines as `layout` in its metadata,
+    /// defaulting to `layouts/main.html` for pages if it exists. The layout itself is rendered as
+    /// a regular file, and can thus define a parent layout of its own.
+    fn apply_layout(&mut self, file: &File, content: String, scope: &Scope) -> Result<String> {
+        let layout_key = self.parse_path("layout")?;
+        // only the default layout is optional, unlike one defined explicitly
+        let (name, optional) = match file.meta().and_then(|meta| meta.value(&*layout_key)) {
+            Some(Value::String(name)) => (name.as_str(), false),
+            Some(Value::Null) => (NO_LAYOUT, false),
+            Some(_) => {
+                return Err(RenderError::UnexpectedLayout(String::from(file.info().path())).into())
+            }
+            None => match file.info().kind() {
+                FileKind::Page => (DEFAULT_LAYOUT, true),
+                FileKind::Include | FileKind::Layout | FileKind::Data => (NO_LAYOUT, false),
+            },
+        };
+        if name.trim().eq_ignore_ascii_case(NO_LAYOUT) {
+            return Ok(content);
+        }
+        let path = self.parse_path(&layout_path(name))?;
+        let layout = match self.workspace.borrow_mut().layout_or_value(&*path) {
+            Some(FileOrValue::File(layout)) => layout.clone(),
+            _ if optional => return Ok(content),
+            _ => return Err(RenderError::LayoutNotFound(String::from(name)).into()),
+        };
+        self.render_file(&layout, &scope.with_content(content))
+    }
+
+    fn render_include(&mut self, include: IncludePath, scope: &Scope) -> Result<String> {
+        let (path, parsed) = match include {
+            IncludePath::Content => {
+                return match &scope.content {
+                    Some(content) => Ok(content.clone()),
+                    None => Err(RenderError::ContentNotFound.into()),
+                }
+            }
+            IncludePath::Meta(path)
+            | IncludePath::OptionalMeta(path)
+            | IncludePath::Site(path)
+            | IncludePath::Data(path)
+            | IncludePath::Include(path) => (path, self.parse_path(path)?),
+        };
+        match include {
+            IncludePath::Content => unreachable!(),
+            // a path with wildcards renders all values it matches,
+            // while optional metadata renders as an empty string when not defined
+            IncludePath::Meta(_) | IncludePath::OptionalMeta(_) => {
+                let values = first_values(&*parsed, &scope.metas);
+                if values.is_empty() && matches!(include, IncludePath::Meta(_)) {
+                    return Err(RenderError::MetaNotFound(String::from(path)).into());
+                }
+                let mut output = String::new();
+                for value in values {
+                    output.push_str(&render_value(path, value)?);
+                }
+                Ok(output)
+            }
+            // the site configuration is optional as well
+            IncludePath::Site(_) => {
+                let mut output = String::new();
+                for value in self.workspace.borrow().config().value_iter(&*parsed) {
+                    output.push_str(&render_value(path, value)?);
+                }
+                Ok(output)
+            }
+            // a path with wildcards renders all values it matches
+            IncludePath::Data(_) => {
+                let mut workspace = self.workspace.borrow_mut();
+                let mut it = workspace.data_iter(&*parsed).peekable();
+                if it.peek().is_none() {
+                    return Err(RenderError::IncludeNotFound(format!("@data{}", path)).into());
+                }
+                let mut output = String::new();
+                for value in it {
+                    output.push_str(&render_value(path, value)?);
+                }
+                Ok(output)
+            }
+            // a path with wildcards or a slice renders all files and values it matches
+            IncludePath::Include(_) if parsed.matches_many() => {
+                let matches: Vec<OwnedFileOrValue> = self
+                    .workspace
+                    .borrow_mut()
+                    .include_or_value_iter(&*parsed)
+                    .map(|file_or_value| match file_or_value {
+                        FileOrValue::File(file) => OwnedFileOrValue::File(file.clone()),
+                        FileOrValue::Value(value) => OwnedFileOrValue::Value(value.clone()),
+                    })
+                    .collect();
+                if matches.is_empty() {
+                    return Err(RenderError::IncludeNotFound(String::from(path)).into());
+                }
+                let mut output = String::new();
+                for file_or_value in matches {
+                    output.push_str(&match file_or_value {
+                        OwnedFileOrValue::File(file) => self.render_file(&file, scope)?,
+                        OwnedFileOrValue::Value(value) => render_value(path, &value)?,
+                    });
+                }
+                Ok(output)
+            }
+            IncludePath::Include(_) => {
+                let file = match self.workspace.borrow_mut().include_or_value(&*parsed) {
+                    None => None,
+                    Some(FileOrValue::Value(value)) => return render_value(path, value),
+                    Some(FileOrValue::File(file)) => Some(file.clone()),
+                };
+                match file {
+                    Some(file) => self.render_file(&file, scope),
+                    None => match self.script_data(&parsed, scope)? {
+                        Some(value) => render_value(path, &value),
+                        None => Err(RenderError::IncludeNotFound(String::from(path)).into()),
+                    },
+                }
+            }
+        }
+    }
+
+    /// Parse the path of an include, reusing the path parsed for any earlier include of it.
+    fn parse_path(&mut self, s: &str) -> Result<Rc<ParsedPath>> {
+        if let Some(path) = self.paths.get(s) {
+            return Ok(Rc::clone(path));
+        }
+        let path = ParsedPath::parse(s)
+            .map_err(|err| RenderError::InvalidPath(String::from(s), err.to_string()))?;
+        let path = Rc::new(path);
+        self.paths.insert(String::from(s), Rc::clone(&path));
+        Ok(path)
+    }
+
+    /// Find a value within the includes, which includes the data output by include scripts.
+    fn include_value(&mut self, path: &str, scope: &Scope) -> Result<Option<Value>> {
+        let parsed = self.parse_path(path)?;
+        match self.workspace.borrow_mut().include_or_value(&*parsed) {
+            None => (),
+            Some(FileOrValue::Value(value)) => return Ok(Some(value.clone())),
+            Some(FileOrValue::File(_)) => {
+                return Err(RenderError::UnexpectedFile(String::from(path)).into())
+            }
+        }
+        self.script_data(&parsed, scope)
+    }
+
+    /// Find a value within the data output by an include script,
+    /// for a path such as `foo.bar` where `foo` is a Bash or Rhai script within the includes.
+    /// Bash scripts are expected to print JSON, while Rhai scripts can return any value.
+    fn script_data(&mut self, path: &ParsedPath, scope: &Scope) -> Result<Option<Value>> {
+        let components = path.components();
+        for index in (1..components.len()).rev() {
+            let is_script_path = components[..index]
+                .iter()
+                .all(|component| matches!(component, PathComponent::Name(_)));
+            if !is_script_path {
+                continue;
+            }
+            let file = match self
+                .workspace
+                .borrow_mut()
+                .include_or_value(&components[..index])
+            {
+                Some(FileOrValue::File(file))
+                    if matches!(file.info().format(), FileFormat::Bash | FileFormat::Rhai) =>
+                {
+                    file.clone()
+                }
+                _ => continue,
+            };
+            self.push_stack(file.info())?;
+            let result = self.run_script_for_data(&file, &scope.with_file(&file));
+            self.stack.pop();
+            let data = result?;
+            return Ok(data.value(&components[index..]).cloned());
+        }
+        Ok(None)
+    }
+
+    fn run_script_for_data(&mut self, file: &File, scope: &Scope) -> Result<Value> {
+        if matches!(file.info().format(), FileFormat::Bash) {
+            let output = self.render_bash(file, scope)?;
+            let data: serde_json::Value = serde_json::from_str(&output).map_err(|err| {
+                RenderError::ScriptFailed(
+                    String::from(file.info().path()),
+                    format!("invalid JSON output: {}", err),
+                )
+            })?;
+            return Ok(data.into());
+        }
+        let ast = self.compile_script(file)?;
+        let value = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut self.script_scope(scope), &ast)
+            .map_err(|err| {
+                RenderError::ScriptFailed(String::from(file.info().path()), err.to_string())
+            })?;
+        Ok(dynamic_to_value(value))
+    }
+
+    /// Run a Bash script and return what it printed as its content. The metadata in scope
+    /// is available as `TSG_META_*` environment variables, e.g. `TSG_META_AUTHOR_NAME`,
+    /// and the includes it references as `TSG_INCLUDE_*` environment variables.
+    fn render_bash(&mut self, file: &File, scope: &Scope) -> Result<String> {
+        if !self.options.bash.enabled {
+            return Err(RenderError::BashDisabled(String::from(file.info().path())).into());
+        }
+        let script = String::from_utf8(file.content().to_vec())?;
+        let mut envs = HashMap::new();
+        flatten_value(
+            SITE_ENV_PREFIX,
+            self.workspace.borrow().config().as_value(),
+            &mut envs,
+        );
+        // outer layers first, such that inner metadata shadows outer metadata
+        for meta in scope.metas.iter().rev() {
+            flatten_value(META_ENV_PREFIX, meta, &mut envs);
+        }
+        // files and mappings cannot be passed as a variable, so they are skipped
+        for (var, paths) in requested_includes(&script) {
+            for path in paths {
+                let value = match self.include_value(&path, scope) {
+                    Ok(Some(value)) => value,
+                    Ok(None) => continue,
+                    Err(err) => match err.downcast_ref::<RenderError>() {
+                        Some(RenderError::UnexpectedFile(_)) => continue,
+                        _ => return Err(err),
+                    },
+                };
+                if !matches!(value, Value::Mapping(_)) {
+                    envs.insert(var, render_value(&path, &value)?);
+                    break;
+                }
+            }
+        }
+        let root = self.workspace.borrow().root().to_path_buf();
+        run_bash(file.info().path(), root, &envs, self.options.bash.timeout).map_err(|err| {
+            RenderError::ScriptFailed(String::from(file.info().path()), err.to_string()).into()
+        })
+    }
+
+    /// Compile a Rhai script.
+    fn compile_script(&self, file: &File) -> Result<AST> {
+        let script = String::from_utf8(file.content().to_vec())?;
+        self.engine.compile(&script).map_err(|err| {
+            RenderError::ScriptFailed(String::from(file.info().path()), err.to_string()).into()
+        })
+    }
+
+    /// Scope of a Rhai script, with the `tsg` object as a constant for the given scope.
+    fn script_scope(&self, scope: &Scope) -> rhai::Scope<'static> {
+        let tsg = Tsg::new(
+            self.workspace.clone(),
+            scope.page.clone(),
+            scope.metas.clone(),
+        );
+        let mut script_scope = rhai::Scope::new();
+        script_scope.push_constant("tsg", tsg);
+        script_scope
+    }
+
+    /// Run a Rhai script and render the value it returns.
+    fn render_script(&mut self, file: &File, scope: &Scope) -> Result<String> {
+        let ast = self.compile_script(file)?;
+        let value = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut self.script_scope(scope), &ast)
+            .map_err(|err| {
+                RenderError::ScriptFailed(String::from(file.info().path()), err.to_string())
+            })?;
+        self.render_dynamic(file.info().path(), value, scope)
+    }
+
+    /// Call the `generate` function of a page script,
+    /// and render all pages it requested using the `generator` object.
+    fn generate_pages(&mut self, page: &File, ast: &AST, scope: &Scope) -> Result<Vec<Page>> {
+        let (locale, locales) = {
+            let workspace = self.workspace.borrow();
+            (workspace.locale().cloned(), workspace.site_locales()?)
+        };
+        let generator = Generator::new(ScriptFile::new(page.clone()), locale.clone(), locales);
+        self.engine
+            .call_fn::<()>(
+                &mut self.script_scope(scope),
+                ast,
+                GENERATE_FN,
+                (generator.clone(),),
+            )
+            .map_err(|err| {
+                RenderError::ScriptFailed(String::from(page.info().path()), err.to_string())
+            })?;
+        let (page, generated_pages) = generator.finish();
+        let mut pages = Vec::with_capacity(generated_pages.len());
+        // the page script is called for every locale, where the pages it generates for
+        // another locale are rendered once the website is rendered for that locale
+        for generated_page in generated_pages {
+            if generated_page.locale != locale {
+                continue;
+            }
+            let path = generated_page_output_path(page.as_file().info(), &generated_page.path)?;
+            let scope = Scope {
+                metas: self.page_scope(page.as_file(), &path)?.metas,
+                ..scope.clone()
+            };
+            pages.push(Page {
+                content: self.render_generated_page(page.as_file(), generated_page, &scope)?,
+                path,
+            });
+        }
+        Ok(pages)
+    }
+
+    fn render_generated_page(
+        &mut self,
+        page: &File,
+        generated_page: GeneratedPage,
+        scope: &Scope,
+    ) -> Result<String> {
+        let mut page = page.clone();
+        if let Value::Mapping(map) = generated_page.meta {
+            for (key, value) in map {
+                let key = PathComponent::Name(key.into());
+                page.meta_mut().set_value(&[key], value);
+            }
+        }
+        let scope = scope.with_file(&page);
+        let content = if generated_page.content.is_string() {
+            let content = generated_page.content.into_string().unwrap();
+            expand_includes(&content, |path| self.render_include(path, &scope))?
+        } else {
+            self.render_dynamic(page.info().path(), generated_page.content, &scope)?
+        };
+        let content = match generated_page.format {
+            FileFormat::Markdown => markdown_to_html(&content),
+            _ => content,
+        };
+        self.apply_layout(&page, content, &scope)
+    }
+
+    /// Render a value returned by a Rhai script, where primitive values are rendered as strings,
+    /// files using the regular pipeline and lists by rendering each of its values.
+    fn render_dynamic(&mut self, path: &str, value: Dynamic, scope: &Scope) -> Result<String> {
+        if value.is_unit() {
+            return Ok(String::new());
+        }
+        if value.is::<ScriptFile>() {
+            return self.render_file(value.cast::<ScriptFile>().as_file(), scope);
+        }
+        if value.is_array() {
+            let mut output = String::new();
+            for value in value.cast::<Array>() {
+                output.push_str(&self.render_dynamic(path, value, scope)?);
+            }
+            return Ok(output);
+        }
+        if value.is_map() {
+            return Err(RenderError::UnexpectedMapping(String::from(path)).into());
+        }
+        Ok(value.to_string())
+    }
+
+    /// Scope of a page rendered to the given output path, with the metadata provided by TSG
+    /// as its most outer layer: the `locale` the page is rendered for, and the `alternates`
+    /// listing the `locale`, `hreflang`, `url` and `link` tag of each localized version of the page.
+    /// The URLs are absolute when the site configuration defines a `base_url`.
+    fn page_scope(&self, page: &File, path: &Path) -> Result<Scope> {
+        let workspace = self.workspace.borrow();
+        let default_locale = workspace.default_locale()?;
+        let base_url = match workspace.config().value(&ParsedPath::parse("base_url")?) {
+            Some(Value::String(base_url)) => base_url.trim_end_matches('/'),
+            _ => "",
+        };
+        let url_path: Vec<String> = path
+            .iter()
+            .map(|s| s.to_string_lossy().into_owned())
+            .collect();
+        let url_path = url_path.join("/");
+        let mut alternates = Vec::new();
+        for locale in workspace.page_locales(page.info())? {
+            let mut push_alternate = |hreflang: String, url: String| {
+                let link = format!(
+                    r#"<link rel="alternate" hreflang="{}" href="{}">"#,
+                    hreflang, url
+                );
+                let mut alternate = IndexMap::with_capacity(4);
+                alternate.insert(
+                    String::from("locale"),
+                    Value::String(locale.as_ref().map(|l| l.to_string()).unwrap_or_default()),
+                );
+                alternate.insert(String::from("hreflang"), Value::String(hreflang));
+                alternate.insert(String::from("url"), Value::String(url));
+                alternate.insert(String::from("link"), Value::String(link));
+                alternates.push(Value::Mapping(alternate));
+            };
+            match &locale {
+                // the root of the website is rendered for the default locale,
+                // which is also the alternate for its own language when it has one
+                _ if locale == default_locale => {
+                    let url = format!("{}/{}", base_url, url_path);
+                    push_alternate(String::from("x-default"), url.clone());
+                    if let Some(locale) = locale.as_ref().filter(|l| l.language().is_some()) {
+                        push_alternate(locale.to_string(), url);
+                    }
+                }
+                // only locales with a language are valid as a hreflang
+                Some(locale) if locale.language().is_some() => push_alternate(
+                    locale.to_string(),
+                    format!("{}/{}/{}", base_url, locale.path_segment(), url_path),
+                ),
+                _ => {}
+            }
+        }
+        let mut layer = IndexMap::with_capacity(2);
+        layer.insert(
+            String::from("locale"),
+            Value::String(
+                workspace
+                    .locale()
+                    .map(|l| l.to_string())
+                    .unwrap_or_default(),
+            ),
+        );
+        layer.insert(String::from("alternates"), Value::Sequence(alternates));
+        Ok(Scope {
+            page: Some(page.clone()),
+            metas: vec![Value::Mapping(layer)],
+            content: None,
+        })
+    }
+
+    fn display_path(&self, info: &FileInfo) -> String {
+        let path = Path::new(info.path());
+        path.strip_prefix(self.workspace.borrow().root())
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Relative path within the publish directory of a rendered page,
+/// mirroring its location within the `pages` directory.
+fn page_output_path(info: &FileInfo) -> Result<PathBuf> {
+    let mut path = PathBuf::new();
+    if let Some(dir) = info.directory() {
+        push_output_path(&mut path, dir)?;
+    }
+    path.push(format!("{}.html", info.name()));
+    Ok(path)
+}
+
+/// Relative path within the publish directory of a page generated by a page script,
+/// which is relative to the location of that script, unless it starts with a `/`.
+/// The extension of a known format is replaced, e.g. `foo.md` is written as `foo.html`.
+fn generated_page_output_path(info: &FileInfo, name: &str) -> Result<PathBuf> {
+    let mut path = PathBuf::new();
+    if !name.starts_with(['/', '\\']) {
+        if let Some(dir) = info.directory() {
+            push_output_path(&mut path, dir)?;
+        }
+    }
+    push_output_path(&mut path, name)?;
+    let stem = match path.file_name() {
+        Some(file_name) => {
+            let file_name = file_name.to_string_lossy();
+            match file_name.rsplit_once('.') {
+                Some((stem, ext)) if ext.parse::<FileFormat>().is_ok() => String::from(stem),
+                _ => file_name.into_owned(),
+            }
+        }
+        None => return Err(RenderError::InvalidOutputPath(String::from(name)).into()),
+    };
+    path.set_file_name(format!("{}.html", stem));
+    Ok(path)
+}
+
+// a page can only be written within the publish directory, so `..` is rejected,
+// as is any root or prefix such as `C:`
+fn push_output_path(path: &mut PathBuf, s: &str) -> Result<()> {
+    for segment in s.split(['/', '\\']).filter(|s| !s.is_empty() && *s != ".") {
+        let mut components = Path::new(segment).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => path.push(segment),
+            _ => return Err(RenderError::InvalidOutputPath(String::from(s)).into()),
+        }
+    }
+    Ok(())
+}
+
+/// Turn a layout name such as `blog.html` or `docs/page.html`
+/// into a path which can be used to find it within the workspace layouts.
+fn layout_path(name: &str) -> String {
+    let name = name.trim();
+    let name = match name.rsplit_once('.') {
+        Some((stem, ext)) if matches!(ext.parse(), Ok(FileFormat::Html)) => stem,
+        _ => name,
+    };
+    name.replace(['/', '\\'], ".")
+}
+
+fn render_value(path: &str, value: &Value) -> Result<String> {
+    Ok(match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Number(x) => x.to_string(),
+        Value::Sequence(seq) => {
+            let mut output = String::new();
+            for value in seq {
+                output.push_str(&render_value(path, value)?);
+            }
+            output
+        }
+        Value::Mapping(_) => return Err(RenderError::UnexpectedMapping(String::from(path)).into()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use crate::test_util::TempDir;
+
+    // render the page of the given name as found within the pages of the workspace
+    fn render_page(root: &Path, name: &str) -> Result<String> {
+        let workspace = Workspace::read(root)?;
+        let page = File::read(root, root.join("pages").join(name))?;
+        Ok(Renderer::new(workspace).render_page(&page)?[0]
+            .content()
+            .to_owned())
+    }
+
+    #[test]
+    fn test_render_page_with_includes() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("examples")
+            .join("basic")
+            .join("one_page_custom_layout");
+        let output = render_page(&root, "index.html").unwrap();
+        assert!(output.contains("The entire file used as a page"));
+        assert!(output.contains("<h3>Introduction</h3>"));
+        assert!(output.contains("This is an example introduction"));
+        assert!(!output.contains("<include>"));
+    }
+
+    #[test]
+    fn test_render_page_with_cyclic_include() {
+        let dir = TempDir::new();
+        let root = dir.path();
+        dir.write("pages/index.md", "<include>a</include>");
+        dir.write("includes/a.md", "<include>b</include>");
+        dir.write("includes/b.html", "<include>a</include>");
+
+        let err = render_page(root, "index.md").unwrap_err();
+        match err.downcast_ref::<RenderError>() {
+            Some(RenderError::CyclicInclude(stack)) => assert_eq!(
+                stack.join(" -> "),
+                "pages/index.md -> includes/a.md -> includes/b.html -> includes/a.md"
+            ),
+            _ => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn test_render_page_with_layouts() {
+        let dir = TempDir::new();
+        let root = dir.path();
+        dir.write("layouts/main.html", "<main><include>$%</include></main>");
+        // the metadata of a layout provides defaults, which the page itself can override
+        dir.write(
+            "layouts/blog.html",
+            "<!--\nlayout: main.html\ntitle: Untitled\n-->\n<article><include>$.title</include><include>$.tags?</include>: <include>$.content</include></article>",
+        );
+        dir.write("pages/index.html", "index");
+        dir.write(
+            "pages/post.html",
+            "<!--\nlayout: blog.html\ntitle: Hello\n-->\npost",
+        );
+        dir.write("pages/draft.html", "<!--\nlayout: blog.html\n-->\ndraft");
+        dir.write("pages/raw.html", "<!--\nlayout: none\n-->\nraw");
+        dir.write("pages/typo.html", "<include>$.titel</include>");
+
+        let test_cases = vec![
+            ("index.html", "<main>index</main>"),
+            ("post.html", "<main><article>Hello: post</article></main>"),
+            (
+                "draft.html",
+                "<main><article>Untitled: draft</article></main>",
+            ),
+            ("raw.html", "raw"),
+        ];
+        for (name, expected_output) in test_cases {
+            assert_eq!(render_page(root, name).unwrap(), expected_output);
+        }
+        // metadata which is not marked as optional has to be defined
+        let err = render_page(root, "typo.html").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RenderError>(),
+            Some(RenderError::MetaNotFound(path)) if path == ".titel"
+        ));
+        // only the default layout is optional
+        dir.write("pages/main.html", "<!--\nlayout: main\n-->\nmain");
+        dir.write(
+            "pages/missing.html",
+            "<!--\nlayout: missing.html\n-->\nmissing",
+        );
+        std::fs::remove_file(root.join("layouts").join("main.html")).unwrap();
+        assert_eq!(render_page(root, "index.html").unwrap(), "index");
+        for (name, layout) in [("main.html", "main"), ("missing.html", "missing.html")] {
+            let err = render_page(root, name).unwrap_err();
+            assert!(
+                matches!(
+                    err.downcast_ref::<RenderError>(),
+                    Some(RenderError::LayoutNotFound(name)) if name == layout
+                ),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_page_with_site_config() {
+        let dir = TempDir::new();
+        let root = dir.path();
+        dir.write("tsg.yml", "siteName: TSG\nlinks:\n  a: 1\n  b: 2");
+        dir.write(
+            "pages/index.html",
+            "<include>$site.siteName</include>|<include>$site.links.*</include>|<include>$site.missing</include>",
+        );
+
+        let output = render_page(root, "index.html").unwrap();
+        assert_eq!(output, "TSG|12|");
+    }
+
+    #[test]
+    fn test_render_page_with_malformed_paths() {
+        let dir = TempDir::new();
+        let root = dir.path();
+        dir.write("includes/foo/bar.md", "bar");
+        dir.write("includes/script.rhai", r#"tsg.includes("foo..bar")"#);
+        dir.write(
+            "includes/file.rhai",
+            r#"tsg.includes("foo.bar").meta("a[")"#,
+        );
+
+        let test_cases = vec![
+            ("<include>foo..bar</include>", "invalid path foo..bar"),
+            ("<include>$.a[</include>", "invalid path .a["),
+            ("<!--\nlayout: a..b.html\n-->\n", "invalid path a..b"),
+            ("<include>script</include>", "invalid path foo..bar"),
+            ("<include>file</include>", "invalid path a["),
+        ];
+        for (content, expected_err) in test_cases {
+            dir.write("pages/index.html", content);
+            let err = render_page(root, "index.html").unwrap_err().to_string();
+            assert!(err.contains(expected_err), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_render_page_with_alternates() {
+        let dir = TempDir::new();
+        let root = dir.path();
+        let page = "<include>$.locale</include>|<include>$.alternates.*.hreflang</include>|\
+                    <include>$.alternates.*.url</include>";
+        dir.write("pages/index.html", page);
+        dir.write("pages/index.nl.html", page);
+        dir.write("pages/index.fr.html", page);
+        dir.write("pages/news.nl.html", page);
+        dir.write("layouts/main.html", "<include>$.content</include>");
+
+        let render = |workspace: Workspace, locale: Option<&str>, name: &str| {
+            let page = File::read(root, root.join("pages").join(name)).unwrap();
+            let mut renderer = Renderer::new(workspace);
+            renderer.set_locale(locale.map(|l| l.parse().unwrap()));
+            renderer.render_page(&page).unwrap()[0].content().to_owned()
+        };
+        let cases = vec![
+            (
+                None,
+                "index.html",
+                "|x-defaultfrnl|/index.html/fr/index.html/nl/index.html",
+            ),
+            (
+                Some("nl"),
+                "index.nl.html",
+                "nl|x-defaultfrnl|/index.html/fr/index.html/nl/index.html",
+            ),
+            // a page without an unlocalized version is not available at the root
+            (Some("nl"), "news.nl.html", "nl|nl|/nl/news.html"),
+        ];
+        for (locale, name, expected) in cases {
+            let workspace = Workspace::read(root).unwrap();
+            assert_eq!(render(workspace, locale, name), expected, "{}", name);
+        }
+
+        // the root is rendered for the default locale, and also listed under its own language
+        dir.write(
+            "tsg.yml",
+            "default_locale: nl\nbase_url: https://example.com/",
+        );
+        let workspace = Workspace::read(root).unwrap();
+        assert_eq!(
+            render(workspace, Some("nl"), "index.nl.html"),
+            "nl|x-defaultnlfr|https://example.com/index.htmlhttps://example.com/index.html\
+             https://example.com/fr/index.html"
+        );
+    }
+
+    #[test]
+    fn test_render_page_with_script() {
+        let dir = TempDir::new();
+        let root = dir.path();
+        dir.write(
+            "pages/index.html",
+            "<!--\ntitle: Hello\n-->\n<include>script</include>",
+        );
+        dir.write("includes/strings.yml", "a: 1\nb: 2");
+        dir.write("includes/intro.md", "*intro*");
+        dir.write(
+            "includes/script.rhai",
+            r#"
+                let sum = 0;
+                for x in tsg.includes("strings.*") {
+                    sum += x;
+                }
+                [tsg.meta("title"), " ", sum, " ", tsg.includes("intro")]
+            "#,
+        );
+
+        let output = render_page(root, "index.html").unwrap();
+        assert_eq!(output, "Hello 3 <p><em>intro</em></p>\n");
+    }
+
+    #[test]
+    fn test_render_page_with_script_files() {
+        let dir = TempDir::new();
+        let root = dir.path();
+        dir.write("pages/index.html", "<include>script</include>");
+        dir.write(
+            "includes/posts/hello.md",
+            "---\ntitle: Hello\ndate: 2021-01-01\n---\n<include>$.title</include>",
+        );
+        dir.write("includes/script.rhai",
+            r#"
+                let output = "";
+                for post in tsg.includes("posts.*") {
+                    output += `${post.meta("title")} (${post.meta("date")}) ${post.type} ${post.content}|`;
+                }
+                let post = tsg.includes("posts.hello");
+                post.set_meta("title", "Bye");
+                [output, post, tsg.includes("posts.hello")]
+            "#,
+        );
+
+        let output = render_page(root, "index.html").unwrap();
+        assert_eq!(
+            output,
+            "Hello (2021-01-01) md <include>$.title</include>|<p>Bye</p>\n<p>Hello</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_render_page_with_bash_scripts() {
+        let dir = TempDir::new();
+        let root = dir.path();
+        dir.write("pages/index.html",
+            "<!--\ntitle: Home\nauthor:\n  name: Glen\n-->\n<include>greeting</include>|<include>stats.posts.count</include>",
+        );
+        dir.write("includes/site/name.yml", "value: TSG");
+        dir.write("includes/greeting.sh",
+            r#"printf '%s by %s for %s in %s' "$TSG_META_TITLE" "$TSG_META_AUTHOR_NAME" "$TSG_INCLUDE_SITE_NAME_VALUE" "$(basename "$PWD")""#,
+        );
+        dir.write("includes/stats.sh", r#"echo '{"posts": {"count": 3}}'"#);
+
+        let output = render_page(root, "index.html").unwrap();
+        // scripts run within the workspace root
+        let root_name = root.file_name().unwrap().to_string_lossy();
+        assert_eq!(output, format!("Home by Glen for TSG in {}|3", root_name));
+    }
+
+    #[test]
+    fn test_render_page_with_bash_include_candidates() {
+        let dir = TempDir::new();
+        let root = dir.path();
+        dir.write("pages/index.html", "<include>greeting</include>");
+        // `strings.site.name` is a mapping, so `strings.site_name` is used instead
+        dir.write(
+            "includes/strings.yml",
+            "site:\n  name:\n    first: Site\nsite_name: TSG",
+        );
+        dir.write("includes/site.md", "site");
+        dir.write("includes/site_title.yml", "value: Title");
+        dir.write(
+            "includes/greeting.sh",
+            r#"printf '%s %s' "$TSG_INCLUDE_STRINGS_SITE_NAME" "$TSG_INCLUDE_SITE_TITLE_VALUE""#,
+        );
+
+        let output = render_page(root, "index.html").unwrap();
+        assert_eq!(output, "TSG Title");
+    }
+
+    #[test]
+    fn test_render_page_with_failing_bash_scripts() {
+        let dir = TempDir::new();
+        let root = dir.path();
+        dir.write(
+            "includes/fail.sh",
+            "echo partial\necho 'something went wrong' >&2\nexit 3",
+        );
+        dir.write("includes/hang.sh", "sleep 5");
+
+        let test_cases = vec![
+            ("fail", RenderOptions::default(), "something went wrong"),
+            (
+                "hang",
+                RenderOptions {
+                    bash: BashOptions {
+                        enabled: true,
+                        timeout: std::time::Duration::from_millis(100),
+                    },
+                },
+                "timed out after 0.1 seconds",
+            ),
+            (
+                "fail",
+                RenderOptions {
+                    bash: BashOptions {
+                        enabled: false,
+                        ..BashOptions::default()
+                    },
+                },
+                "bash is disabled",
+            ),
+        ];
+        for (include, options, expected_err) in test_cases {
+            dir.write(
+                "pages/index.html",
+                format!("<include>{}</include>", include),
+            );
+            let workspace = Workspace::read(root).unwrap();
+            let page = File::read(root, root.join("pages").join("index.html")).unwrap();
+            let err = Renderer::with_options(workspace, options)
+                .render_page(&page)
+                .unwrap_err()
+                .to_string();
+            assert!(err.contains(&format!("{}.sh", include)), "{}", err);
+            assert!(err.contains(expected_err), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_render_page_with_generator() {
+        let dir = TempDir::new();
+        let root = dir.path();
+        dir.write(
+            "layouts/main.html",
+            "<include>$.title</include>|<include>$.index</include>|<include>$%</include>",
+        );
+        dir.write(
+            "pages/docs/list.rhai",
+            r#"---
+title: List
+---
+fn generate(generator) {
+    for index in 1..=2 {
+        generator.md(`page/${index}`, "_<include>$.index</include>_", #{ index: index });
+    }
+    generator.html("/index", 42, #{ layout: "none" });
+    generator.md("page/3.md", "3", #{ layout: "none" });
+}
+"#,
+        );
+
+        let workspace = Workspace::read(root).unwrap();
+        let page = File::read(root, root.join("pages").join("docs").join("list.rhai")).unwrap();
+        let pages = Renderer::new(workspace).render_page(&page).unwrap();
+        let pages: Vec<(String, &str)> = pages
+            .iter()
+            .map(|page| (page.path().to_string_lossy().into_owned(), page.content()))
+            .collect();
+        assert_eq!(
+            pages,
+            vec![
+                (
+                    String::from("docs/page/1.html"),
+                    "List|1|<p><em>1</em></p>\n"
+                ),
+                (
+                    String::from("docs/page/2.html"),
+                    "List|2|<p><em>2</em></p>\n"
+                ),
+                (String::from("index.html"), "42"),
+                (String::from("docs/page/3.html"), "<p>3</p>\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_page_with_generator_outside_output() {
+        let dir = TempDir::new();
+        let root = dir.path();
+        for name in ["../../escaped", "/../escaped", "a/../../escaped", "a/.."] {
+            dir.write(
+                "pages/docs/list.rhai",
+                format!(
+                    "fn generate(generator) {{ generator.html({:?}, \"x\"); }}",
+                    name
+                ),
+            );
+            let err = render_page(root, "docs/list.rhai").unwrap_err();
+            assert!(
+                matches!(
+                    err.downcast_ref::<RenderError>(),
+                    Some(RenderError::InvalidOutputPath(path)) if path == name
+                ),
+                "{}: {}",
+                name,
+                err
+            );
+        }
+    }
+}
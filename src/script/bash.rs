@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use wait_timeout::ChildExt;
+
+use crate::io::Value;
+
+/// Prefix of the environment variables containing the metadata in scope of a Bash script.
+pub const META_ENV_PREFIX: &str = "TSG_META";
+/// Prefix of the environment variables containing the site configuration.
+pub const SITE_ENV_PREFIX: &str = "TSG_SITE";
+/// Prefix of the environment variables containing the values included by a Bash script.
+pub const INCLUDE_ENV_PREFIX: &str = "TSG_INCLUDE";
+/// Time a Bash script is given to finish by default, before it gets killed.
+pub const DEFAULT_BASH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Options which define if and how Bash scripts are run.
+#[derive(Debug, Clone)]
+pub struct BashOptions {
+    /// Disable this to fail on any Bash script, e.g. when building an untrusted workspace.
+    pub enabled: bool,
+    /// Time each script run is given to finish, before it gets killed.
+    pub timeout: Duration,
+}
+
+impl Default for BashOptions {
+    fn default() -> BashOptions {
+        BashOptions {
+            enabled: true,
+            timeout: DEFAULT_BASH_TIMEOUT,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BashError {
+    Exited(ExitStatus, String),
+    TimedOut(Duration),
+}
+
+impl Error for BashError {}
+
+impl fmt::Display for BashError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BashError::Exited(status, stderr) => {
+                write!(f, "{}", status)?;
+                match stderr.trim() {
+                    "" => Ok(()),
+                    stderr => write!(f, ":\n{}", stderr),
+                }
+            }
+            BashError::TimedOut(timeout) => {
+                write!(f, "timed out after {} seconds", timeout.as_secs_f64())
+            }
+        }
+    }
+}
+
+/// Run a Bash script with the given root as its working directory,
+/// returning everything it printed to its STDOUT. The script is killed
+/// when it did not finish within the given timeout.
+pub fn run_bash<P, Q>(
+    script: P,
+    root: Q,
+    envs: &HashMap<String, String>,
+    timeout: Duration,
+) -> Result<String>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let script = std::path::absolute(script.as_ref())?;
+    let mut child = Command::new("bash")
+        .arg(&script)
+        .current_dir(root)
+        .envs(envs)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // output is read while waiting, as a script blocks once its output pipes are full
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+    let status = match child.wait_timeout(timeout)? {
+        Some(status) => status,
+        None => {
+            // the script might have finished just now, in which case it can no longer be killed
+            let _ = child.kill();
+            child.wait()?;
+            return Err(BashError::TimedOut(timeout).into());
+        }
+    };
+    let stdout = join_output(stdout)?;
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&join_output(stderr)?).into_owned();
+        return Err(BashError::Exited(status, stderr).into());
+    }
+    Ok(String::from_utf8(stdout)?)
+}
+
+fn read_in_background<R>(pipe: Option<R>) -> JoinHandle<std::io::Result<Vec<u8>>>
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_end(&mut output)?;
+        }
+        Ok(output)
+    })
+}
+
+fn join_output(handle: JoinHandle<std::io::Result<Vec<u8>>>) -> Result<Vec<u8>> {
+    Ok(handle
+        .join()
+        .map_err(|_| anyhow!("failed to read the output of the script"))??)
+}
+
+/// Name of the environment variable for the given prefix and path,
+/// e.g. `TSG_META_AUTHOR_NAME` for the `author.name` metadata path.
+pub fn env_var_name<S: AsRef<str>>(prefix: &str, path: &[S]) -> String {
+    let mut name = String::from(prefix);
+    for component in path {
+        name.push('_');
+        name.extend(component.as_ref().chars().map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => c.to_ascii_uppercase(),
+            _ => '_',
+        }));
+    }
+    name
+}
+
+/// Flatten a value into environment variables, one per primitive value found within it.
+pub fn flatten_value(prefix: &str, value: &Value, envs: &mut HashMap<String, String>) {
+    flatten_value_at(prefix, &mut Vec::new(), value, envs)
+}
+
+fn flatten_value_at(
+    prefix: &str,
+    path: &mut Vec<String>,
+    value: &Value,
+    envs: &mut HashMap<String, String>,
+) {
+    let primitive = match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Number(x) => x.to_string(),
+        Value::Sequence(seq) => {
+            for (index, value) in seq.iter().enumerate() {
+                path.push(index.to_string());
+                flatten_value_at(prefix, path, value, envs);
+                path.pop();
+            }
+            return;
+        }
+        Value::Mapping(map) => {
+            for (key, value) in map {
+                path.push(key.clone());
+                flatten_value_at(prefix, path, value, envs);
+                path.pop();
+            }
+            return;
+        }
+    };
+    if !path.is_empty() {
+        envs.insert(env_var_name(prefix, path), primitive);
+    }
+}
+
+/// All include paths a Bash script might request, derived from the `TSG_INCLUDE_*`
+/// environment variables it references. As an underscore in such a variable can originate
+/// from either a path separator or the name itself, each variable results in one or more
+/// candidate paths, ordered from most to least path separators.
+pub fn requested_includes(script: &str) -> Vec<(String, Vec<String>)> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(&format!(
+            r"{}_(?P<name>[A-Za-z0-9_]*[A-Za-z0-9])",
+            INCLUDE_ENV_PREFIX
+        ))
+        .unwrap();
+    }
+    let mut requests: Vec<(String, Vec<String>)> = Vec::new();
+    for caps in RE.captures_iter(script) {
+        let var = String::from(&caps[0]);
+        if requests.iter().any(|(other, _)| other == &var) {
+            continue;
+        }
+        let parts: Vec<String> = caps["name"]
+            .split('_')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_lowercase())
+            .collect();
+        requests.push((var, path_candidates(&parts)));
+    }
+    requests
+}
+
+fn path_candidates(parts: &[String]) -> Vec<String> {
+    // limit the amount of candidates for unreasonably long names
+    if parts.len() > 8 {
+        return vec![parts.join("."), parts.join("_")];
+    }
+    let separators = parts.len().saturating_sub(1);
+    let mut candidates: Vec<(u32, String)> = (0..1u32 << separators)
+        .map(|mask| {
+            let mut path = parts[0].clone();
+            for (index, part) in parts.iter().enumerate().skip(1) {
+                path.push(if mask & (1 << (index - 1)) == 0 {
+                    '.'
+                } else {
+                    '_'
+                });
+                path.push_str(part);
+            }
+            (mask.count_ones(), path)
+        })
+        .collect();
+    candidates.sort_by_key(|(underscores, _)| *underscores);
+    candidates.into_iter().map(|(_, path)| path).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requested_includes() {
+        let test_cases = vec![
+            ("echo hello", vec![]),
+            (
+                r#"baz="$TSG_INCLUDE_FOO_BAR_BAZ" && echo "${TSG_INCLUDE_INTRO} $TSG_INCLUDE_FOO_BAR_BAZ""#,
+                vec![
+                    (
+                        "TSG_INCLUDE_FOO_BAR_BAZ",
+                        vec!["foo.bar.baz", "foo_bar.baz", "foo.bar_baz", "foo_bar_baz"],
+                    ),
+                    ("TSG_INCLUDE_INTRO", vec!["intro"]),
+                ],
+            ),
+        ];
+        for (input, expected_output) in test_cases {
+            let output = requested_includes(input);
+            let expected_output: Vec<(String, Vec<String>)> = expected_output
+                .into_iter()
+                .map(|(var, paths)| {
+                    (
+                        String::from(var),
+                        paths.into_iter().map(String::from).collect(),
+                    )
+                })
+                .collect();
+            assert_eq!(output, expected_output);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+use rhai::module_resolvers::FileModuleResolver;
+use rhai::{Array, Dynamic, Engine, EvalAltResult};
+
+use super::{value_to_dynamic, Generator, ScriptFile};
+use crate::io::data::first_value;
+use crate::io::path::Path as ParsedPath;
+use crate::io::{File, FileOrValue, FileOrValueIter, Value, Workspace};
+
+/// Create a Rhai engine which knows about the TSG types,
+/// and which resolves imported modules relative to the includes of the workspace.
+pub fn new_engine<P: AsRef<Path>>(root: P) -> Engine {
+    let mut engine = Engine::new();
+    // use the same limits as release builds of Rhai, such that scripts
+    // behave the same regardless of how TSG was built
+    engine.set_max_expr_depths(64, 32);
+    engine.set_module_resolver(FileModuleResolver::new_with_path(
+        root.as_ref().join("includes"),
+    ));
+
+    engine
+        .register_type_with_name::<ScriptFile>("File")
+        .register_fn("meta", ScriptFile::meta)
+        .register_fn("set_meta", ScriptFile::set_meta)
+        .register_get("content", ScriptFile::content)
+        .register_get("path", ScriptFile::path)
+        .register_get("locale", ScriptFile::locale)
+        .register_get("name", ScriptFile::name)
+        .register_get("type", ScriptFile::file_type);
+
+    engine
+        .register_type_with_name::<Generator>("Generator")
+        .register_get_set("page", Generator::page, Generator::set_page)
+        .register_get_set("locale", Generator::locale, Generator::set_locale)
+        .register_get("locales", Generator::locales)
+        .register_fn("html", Generator::html)
+        .register_fn("html", Generator::html_with_meta)
+        .register_fn("md", Generator::md)
+        .register_fn("md", Generator::md_with_meta);
+
+    engine
+        .register_type_with_name::<Tsg>("Tsg")
+        .register_fn("includes", Tsg::includes)
+        .register_fn("pages", Tsg::page)
+        .register_fn("pages", Tsg::pages)
+        .register_fn("meta", Tsg::meta)
+        .register_fn("site", Tsg::site)
+        .register_fn("data", Tsg::data)
+        .register_get("locale", Tsg::locale);
+
+    engine
+}
+
+/// The `tsg` object in scope of every Rhai script.
+#[derive(Clone)]
+pub struct Tsg {
+    workspace: Rc<RefCell<Workspace>>,
+    page: Option<File>,
+    metas: Vec<Value>,
+}
+
+impl Tsg {
+    /// Create the `tsg` object for a script rendered as part of the given page,
+    /// with the metadata in scope ordered from the most inner layer to the most outer layer.
+    pub fn new(workspace: Rc<RefCell<Workspace>>, page: Option<File>, metas: Vec<Value>) -> Tsg {
+        Tsg {
+            workspace,
+            page,
+            metas,
+        }
+    }
+
+    /// Locale the page is rendered for as a BCP 47 language tag (e.g. `en-GB`),
+    /// or an empty string for the unlocalized page.
+    fn locale(&mut self) -> String {
+        match self.workspace.borrow().locale() {
+            Some(locale) => locale.to_string(),
+            None => String::new(),
+        }
+    }
+
+    fn includes(&mut self, path: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        let path = parse_path(path)?;
+        let mut workspace = self.workspace.borrow_mut();
+        Ok(files_or_values_to_dynamic(
+            &path,
+            workspace.include_or_value_iter(&path),
+        ))
+    }
+
+    fn page(&mut self) -> Dynamic {
+        match &self.page {
+            Some(page) => Dynamic::from(ScriptFile::new(page.clone())),
+            None => Dynamic::UNIT,
+        }
+    }
+
+    fn pages(&mut self, path: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        let path = parse_path(path)?;
+        let mut workspace = self.workspace.borrow_mut();
+        Ok(files_or_values_to_dynamic(
+            &path,
+            workspace.page_or_value_iter(&path),
+        ))
+    }
+
+    fn site(&mut self, path: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        let path = parse_path(path)?;
+        Ok(match self.workspace.borrow().config().value(&path) {
+            Some(value) => value_to_dynamic(value),
+            None => Dynamic::UNIT,
+        })
+    }
+
+    fn data(&mut self, path: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        let path = parse_path(path)?;
+        let mut workspace = self.workspace.borrow_mut();
+        let mut it = workspace.data_iter(&path).map(value_to_dynamic);
+        Ok(if path.matches_many() {
+            Dynamic::from(it.collect::<Array>())
+        } else {
+            it.next().unwrap_or(Dynamic::UNIT)
+        })
+    }
+
+    fn meta(&mut self, path: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        let path = parse_path(path)?;
+        Ok(match first_value(&path, &self.metas) {
+            Some(value) => value_to_dynamic(value),
+            None => Dynamic::UNIT,
+        })
+    }
+}
+
+/// Parse a path passed to a function by a script, failing the script when it is malformed.
+pub(super) fn parse_path(path: &str) -> Result<ParsedPath, Box<EvalAltResult>> {
+    ParsedPath::parse(path).map_err(|err| format!("invalid path {}: {}", path, err).into())
+}
+
+/// Return all found files and values as a list in case the path contains a wildcard or slice,
+/// and otherwise only the first found file or value.
+fn files_or_values_to_dynamic(path: &ParsedPath, it: FileOrValueIter) -> Dynamic {
+    let mut it = it.map(|file_or_value| match file_or_value {
+        FileOrValue::File(file) => Dynamic::from(ScriptFile::new(file.clone())),
+        FileOrValue::Value(value) => value_to_dynamic(value),
+    });
+    if path.matches_many() {
+        Dynamic::from(it.collect::<Array>())
+    } else {
+        it.next().unwrap_or(Dynamic::UNIT)
+    }
+}
@@ -0,0 +1,78 @@
+use std::path::Path;
+use std::rc::Rc;
+
+use rhai::{Dynamic, EvalAltResult};
+
+use super::engine::parse_path;
+use super::{dynamic_to_value, value_to_dynamic};
+use crate::io::File;
+
+/// A File as exposed to Rhai scripts.
+///
+/// Clones share the underlying File until their metadata is modified,
+/// and such modifications never affect the File as found in the workspace.
+#[derive(Clone)]
+pub struct ScriptFile {
+    file: Rc<File>,
+}
+
+impl ScriptFile {
+    pub fn new(file: File) -> ScriptFile {
+        ScriptFile {
+            file: Rc::new(file),
+        }
+    }
+
+    pub fn as_file(&self) -> &File {
+        &self.file
+    }
+
+    pub fn meta(&mut self, path: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        let path = parse_path(path)?;
+        Ok(match self.file.meta().and_then(|meta| meta.value(&path)) {
+            Some(value) => value_to_dynamic(value),
+            None => Dynamic::UNIT,
+        })
+    }
+
+    pub fn set_meta(&mut self, path: &str, value: Dynamic) -> Result<(), Box<EvalAltResult>> {
+        let parsed = parse_path(path)?;
+        let file = Rc::make_mut(&mut self.file);
+        if file.meta_mut().set_value(&parsed, dynamic_to_value(value)) {
+            Ok(())
+        } else {
+            Err(format!("cannot set metadata at path {}", path).into())
+        }
+    }
+
+    pub fn content(&mut self) -> String {
+        String::from_utf8_lossy(self.file.content()).into_owned()
+    }
+
+    pub fn path(&mut self) -> String {
+        let path = Path::new(self.file.info().path());
+        std::path::absolute(path)
+            .as_deref()
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    pub fn name(&mut self) -> String {
+        String::from(self.file.info().name())
+    }
+
+    pub fn locale(&mut self) -> String {
+        match self.file.info().locale() {
+            Some(locale) => locale.to_string(),
+            None => String::new(),
+        }
+    }
+
+    pub fn file_type(&mut self) -> String {
+        Path::new(self.file.info().path())
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,139 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rhai::{Array, Dynamic, EvalAltResult, Map};
+
+use super::{dynamic_to_value, ScriptFile};
+use crate::io::{FileFormat, FileLocale, Value};
+
+/// A page requested by a page script to be generated.
+pub struct GeneratedPage {
+    /// output path of the page, relative to the directory of the page script
+    pub path: String,
+    /// format of the content, either HTML or Markdown
+    pub format: FileFormat,
+    pub content: Dynamic,
+    /// metadata of the page, added on top of the metadata of the page script
+    pub meta: Value,
+    /// locale of the page, `None` for the unlocalized page
+    pub locale: Option<FileLocale>,
+}
+
+/// The `generator` object passed to the `generate` function of a page script.
+#[derive(Clone)]
+pub struct Generator {
+    state: Rc<RefCell<GeneratorState>>,
+}
+
+struct GeneratorState {
+    page: ScriptFile,
+    pages: Vec<GeneratedPage>,
+    // locale of the pages generated from now on
+    locale: Option<FileLocale>,
+    // all locales the website is built for
+    locales: Vec<Option<FileLocale>>,
+}
+
+impl Generator {
+    /// Create the `generator` object for a page script rendered for the given locale,
+    /// which is one of the given locales the website is built for.
+    pub fn new(
+        page: ScriptFile,
+        locale: Option<FileLocale>,
+        locales: Vec<Option<FileLocale>>,
+    ) -> Generator {
+        Generator {
+            state: Rc::new(RefCell::new(GeneratorState {
+                page,
+                pages: Vec::new(),
+                locale,
+                locales,
+            })),
+        }
+    }
+
+    /// The page script, including any metadata modified by the script,
+    /// and all pages requested by the script.
+    pub fn finish(&self) -> (ScriptFile, Vec<GeneratedPage>) {
+        let mut state = self.state.borrow_mut();
+        (state.page.clone(), std::mem::take(&mut state.pages))
+    }
+
+    pub fn page(&mut self) -> ScriptFile {
+        self.state.borrow().page.clone()
+    }
+
+    pub fn set_page(&mut self, page: ScriptFile) {
+        self.state.borrow_mut().page = page;
+    }
+
+    /// Locale of the pages generated from now on as a BCP 47 language tag (e.g. `en-GB`),
+    /// or an empty string for the unlocalized page.
+    pub fn locale(&mut self) -> String {
+        locale_to_string(self.state.borrow().locale.as_ref())
+    }
+
+    /// Generate the next pages for the given locale, which has to be one of the locales
+    /// the website is built for, such that they are written to the tree of that locale.
+    pub fn set_locale(&mut self, locale: String) -> Result<(), Box<EvalAltResult>> {
+        let mut state = self.state.borrow_mut();
+        let locale = match locale.as_str() {
+            "" => None,
+            _ => Some(
+                locale
+                    .parse::<FileLocale>()
+                    .map_err(|err| format!("invalid locale {}: {}", locale, err))?,
+            ),
+        };
+        if !state.locales.contains(&locale) {
+            return Err(format!("unknown locale {}", locale_to_string(locale.as_ref())).into());
+        }
+        state.locale = locale;
+        Ok(())
+    }
+
+    /// All locales the website is built for, starting with the locale of its root.
+    pub fn locales(&mut self) -> Array {
+        self.state
+            .borrow()
+            .locales
+            .iter()
+            .map(|locale| Dynamic::from(locale_to_string(locale.as_ref())))
+            .collect()
+    }
+
+    pub fn html(&mut self, path: &str, content: Dynamic) {
+        self.push(path, FileFormat::Html, content, Map::new());
+    }
+
+    pub fn html_with_meta(&mut self, path: &str, content: Dynamic, meta: Map) {
+        self.push(path, FileFormat::Html, content, meta);
+    }
+
+    pub fn md(&mut self, path: &str, content: Dynamic) {
+        self.push(path, FileFormat::Markdown, content, Map::new());
+    }
+
+    pub fn md_with_meta(&mut self, path: &str, content: Dynamic, meta: Map) {
+        self.push(path, FileFormat::Markdown, content, meta);
+    }
+
+    fn push(&mut self, path: &str, format: FileFormat, content: Dynamic, meta: Map) {
+        let mut state = self.state.borrow_mut();
+        let locale = state.locale.clone();
+        state.pages.push(GeneratedPage {
+            path: String::from(path),
+            format,
+            content,
+            meta: dynamic_to_value(Dynamic::from(meta)),
+            locale,
+        });
+    }
+}
+
+fn locale_to_string(locale: Option<&FileLocale>) -> String {
+    match locale {
+        Some(locale) => locale.to_string(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,17 @@
+mod bash;
+pub use bash::{
+    env_var_name, flatten_value, requested_includes, run_bash, BashError, BashOptions,
+    DEFAULT_BASH_TIMEOUT, INCLUDE_ENV_PREFIX, META_ENV_PREFIX, SITE_ENV_PREFIX,
+};
+
+mod engine;
+pub use engine::{new_engine, Tsg};
+
+mod file;
+pub use file::ScriptFile;
+
+mod generator;
+pub use generator::{GeneratedPage, Generator};
+
+mod value;
+pub use value::{dynamic_to_value, value_to_dynamic};
@@ -0,0 +1,63 @@
+use rhai::{Array, Dynamic, Map, FLOAT, INT};
+
+use crate::io::Value;
+
+/// Convert a TSG value into a Rhai value,
+/// using an integer for numbers which do not have a fractional part.
+pub fn value_to_dynamic(value: &Value) -> Dynamic {
+    match value {
+        Value::Null => Dynamic::UNIT,
+        Value::String(s) => Dynamic::from(s.clone()),
+        Value::Boolean(b) => Dynamic::from(*b),
+        Value::Number(x) => {
+            if x.fract() == 0.0 && *x >= INT::MIN as FLOAT && *x <= INT::MAX as FLOAT {
+                Dynamic::from(*x as INT)
+            } else {
+                Dynamic::from(*x as FLOAT)
+            }
+        }
+        Value::Sequence(seq) => {
+            let arr: Array = seq.iter().map(value_to_dynamic).collect();
+            Dynamic::from(arr)
+        }
+        Value::Mapping(map) => {
+            let map: Map = map
+                .iter()
+                .map(|(k, v)| (k.as_str().into(), value_to_dynamic(v)))
+                .collect();
+            Dynamic::from(map)
+        }
+    }
+}
+
+/// Convert a Rhai value into a TSG value,
+/// where values of any type unknown to TSG are converted into their string representation.
+pub fn dynamic_to_value(value: Dynamic) -> Value {
+    if value.is_unit() {
+        Value::Null
+    } else if let Ok(b) = value.as_bool() {
+        Value::Boolean(b)
+    } else if let Ok(x) = value.as_int() {
+        Value::Number(x as f64)
+    } else if let Ok(x) = value.as_float() {
+        Value::Number(x)
+    } else if value.is_array() {
+        Value::Sequence(
+            value
+                .cast::<Array>()
+                .into_iter()
+                .map(dynamic_to_value)
+                .collect(),
+        )
+    } else if value.is_map() {
+        Value::Mapping(
+            value
+                .cast::<Map>()
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), dynamic_to_value(v)))
+                .collect(),
+        )
+    } else {
+        Value::String(value.to_string())
+    }
+}
@@ -0,0 +1,47 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Directory within the temporary directory of the system, unique to the test which creates it,
+/// and removed together with its content when dropped, also when the test panics.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new() -> TempDir {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "tsg-test-{}-{}",
+            process::id(),
+            COUNT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write a file at the given path relative to the directory,
+    /// such as `pages/index.html`, creating its parent directories as needed.
+    pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&self, path: P, content: C) {
+        let path = self.path.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Parse a path of a test case, which is expected to be well-formed.
+pub fn parse_path(s: &str) -> crate::io::path::Path {
+    crate::io::path::Path::parse(s).unwrap()
+}